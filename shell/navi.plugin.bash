@@ -25,6 +25,15 @@ _navi_widget() {
    READLINE_POINT=${#READLINE_LINE}
 }
 
+_navi_again_widget() {
+   local -r output="$(_navi_call --print again)"
+
+   if [ -n "$output" ]; then
+      READLINE_LINE="$output"
+      READLINE_POINT=${#READLINE_LINE}
+   fi
+}
+
 _navi_widget_legacy() {
    _navi_call --print
 }
@@ -33,4 +42,8 @@ if [ ${BASH_VERSION:0:1} -lt 4 ]; then
    bind '"\C-g": " \C-b\C-k \C-u`_navi_widget_legacy`\e\C-e\C-a\C-y\C-h\C-e\e \C-y\ey\C-x\C-x\C-f"'
 else
    bind -x '"\C-g": _navi_widget'
+   # Alt+G replaces glob-complete-word: NAVI_AGAIN_KEY binds another key, or none when empty
+   if [ -n "${NAVI_AGAIN_KEY-x}" ]; then
+      bind -x "\"${NAVI_AGAIN_KEY:-\eg}\": _navi_again_widget"
+   fi
 fi
\ No newline at end of file
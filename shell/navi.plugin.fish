@@ -41,5 +41,22 @@ function _navi_smart_replace
     end
 end
 
+function _navi_again
+    set --local candidate (navi --print again)
+    if test -n "$candidate"
+        commandline --current-process $candidate
+    end
+    commandline --function repaint
+end
+
 bind \cg _navi_smart_replace
 bind --mode insert \cg _navi_smart_replace
+# NAVI_AGAIN_KEY binds another key than Alt+G, or none when empty
+set --local again_key \eg
+if set --query NAVI_AGAIN_KEY
+    set again_key $NAVI_AGAIN_KEY
+end
+if test -n "$again_key"
+    bind $again_key _navi_again
+    bind --mode insert $again_key _navi_again
+end
@@ -31,14 +31,16 @@ fn main() -> anyhow::Result<()> {
     }
     navi::handle().map_err(|e| {
         error!("{e:?}");
+        // A snippet that failed isn't a problem of navi, which exits with its status like a shell
+        if let Some(failed) = e.downcast_ref::<navi::ExecutionFailed>() {
+            std::process::exit(failed.exit_code());
+        }
         FileAnIssue::new(e).into()
     })
 }
 
 fn init_logger() -> anyhow::Result<()> {
-    const FILE_NAME: &str = "navi.log";
-    let mut file = navi::default_config_pathbuf()?;
-    file.set_file_name(FILE_NAME);
+    let file = navi::log_pathbuf()?;
 
     // If config path doesn't exist, navi won't log.
     if file.parent().map(|p| !p.exists()).unwrap_or(true) {
@@ -0,0 +1,18 @@
+use crate::commands::history::edit;
+use crate::history;
+use crate::prelude::*;
+use clap::Args;
+
+#[derive(Debug, Clone, Args)]
+pub struct Input {}
+
+impl Runnable for Input {
+    fn run(&self) -> Result<()> {
+        let entries = history::read()?;
+        let last = entries
+            .last()
+            .ok_or_else(|| anyhow!("No snippet was selected yet"))?;
+
+        edit(last)
+    }
+}
@@ -1,40 +1,114 @@
 use crate::common::clipboard;
 use crate::common::fs;
-use crate::common::shell;
-use crate::common::shell::ShellSpawnError;
+use crate::common::quoting::{self, Syntax};
+use crate::common::shell::{self, Interpreter};
 use crate::common::types::{EnvVars, VariableCache};
 use crate::config::Action;
 use crate::display;
 use crate::env_var;
-use crate::finder::structures::{Opts as FinderOpts, SuggestionType};
+use crate::finder::structures::{Opts as FinderOpts, Quote, SuggestionType};
+use crate::finder::{self, json};
+use crate::history;
+use crate::policy::{self, Verdict};
 use crate::prelude::*;
 use crate::structures::cheat::{Suggestion, VariableMap};
 use crate::structures::item::Item;
 use std::io::Write as _;
+use std::process::{Child, Command, ExitStatus};
+use std::time::Instant;
+use thiserror::Error;
 
+use super::foreach;
 use super::preview;
 use super::suggestion;
 
+/// Snippets which ran but didn't succeed. navi exits with the status they carry instead of
+/// reporting an error.
+#[derive(Error, Debug)]
+pub enum ExecutionFailed {
+    #[error("The command exited with {0}")]
+    Status(ExitStatus),
+    #[error("{0} of the runs failed")]
+    RunsFailed(usize),
+}
+
+impl ExecutionFailed {
+    /// The exit code of the command, or 1 if it has none, e.g. when it was killed by a signal.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Status(status) => status.code().unwrap_or(1),
+            Self::RunsFailed(_) => 1,
+        }
+    }
+}
+
+/// Fails with the status of a snippet that didn't succeed.
+fn ensure_success(status: ExitStatus) -> Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ExecutionFailed::Status(status).into())
+    }
+}
+
+/// The lines selected for the variables of a snippet, before options such as `--column`
+/// or `--map` turn them into values, so that its prompts can start with them again.
+#[derive(Debug, Default)]
+pub struct Selections {
+    /// The lines selected the previous time, used when `<var>__query` isn't set
+    pub previous: VariableCache,
+    /// The lines selected this time
+    pub current: VariableCache,
+}
+
+/// The query selecting a line again, i.e. its displayed text.
+fn query_of(selection: &str) -> String {
+    let line = selection.trim_end_matches('\n');
+    line.split(display::terminal::DELIMITER)
+        .next()
+        .unwrap_or(line)
+        .to_string()
+}
+
+/// Prompts for the value of a variable, along with the lines selected for it.
 fn prompt_finder(
     variable_name: &str,
     suggestion_option: Option<&Suggestion>,
     variable_count: usize,
     preview_context_env_vars: &EnvVars,
     variable_cache: &VariableCache,
-) -> Result<String> {
+    previous: Option<&str>,
+) -> Result<(String, String)> {
     let mut preview_env_vars = preview_context_env_vars.clone();
 
     // Execute suggestion command and get options
     let (suggestions_text, finder_opts) = if let Some((command, opts)) = suggestion_option {
-        // Apply suggestion options to preview environment variables
-        let _extra_preview = opts
-            .as_ref()
-            .and_then(|o| suggestion::apply_suggestion_options(&mut preview_env_vars, o));
+        let interpreter = opts.as_ref().and_then(|o| o.shell.as_deref());
+        let mut text =
+            suggestion::execute_suggestion_command(command, interpreter, variable_cache)?;
+
+        let mut opts = opts.clone();
+        if let Some(o) = opts.as_mut() {
+            // Columns named in the header line are known once the suggestions are
+            if let Some(columns) = &o.column {
+                let columns = finder::resolve_columns(
+                    columns,
+                    &text,
+                    o.header_lines,
+                    o.delimiter.as_deref(),
+                )?;
+                o.column = Some(columns);
+            }
+            if let Some(fields) = &o.json {
+                text = json::lines(&text, fields)?;
+            }
 
-        let text = suggestion::execute_suggestion_command(command, variable_cache)?;
+            // Apply suggestion options to preview environment variables
+            let _extra_preview = suggestion::apply_suggestion_options(&mut preview_env_vars, o);
+        }
         (text, opts)
     } else {
-        ("\n".to_string(), &None)
+        ("\n".to_string(), None)
     };
 
     // Build shell-specific preview command
@@ -52,17 +126,25 @@ fn prompt_finder(
 
     // Apply variable-specific query and filter
     opts.query = env_var::get(format!("{variable_name}__query")).ok();
+    if opts.query.is_none()
+        && let Some(selection) = previous
+    {
+        // The previous selection is shown for editing, even if it's the only matching suggestion
+        opts.query = Some(query_of(selection));
+        opts.prevent_select1 = true;
+    }
 
     if let Ok(filter) = env_var::get(format!("{variable_name}__best")) {
         opts.filter = Some(filter);
         opts.suggestion_type = SuggestionType::SingleSelection;
     }
 
-    // Set preview window layout
+    // Set preview window layout, with room for the objects of JSON suggestions
     if opts.preview_window.is_none() {
         opts.preview_window = Some(preview::calculate_preview_window(
             extra_preview,
             variable_count,
+            opts.json.is_some(),
         ));
     }
 
@@ -72,7 +154,7 @@ fn prompt_finder(
     }
 
     // Call finder with suggestions
-    let (output, _) = crate::finder::call(opts, |stdin| {
+    let (output, selection, _) = crate::finder::call_with_selection(opts, |stdin| {
         stdin
             .write_all(suggestions_text.as_bytes())
             .context("Could not write to finder's stdin")?;
@@ -80,7 +162,48 @@ fn prompt_finder(
     })
     .context("Finder was unable to prompt with suggestions")?;
 
-    Ok(output)
+    Ok((output, selection))
+}
+
+/// Whether the values of a variable are written into snippets as is, because of `--raw`,
+/// or because `--quote` or `--expand` already quote them.
+pub fn is_raw(variables: &VariableMap, tags: &str, variable_name: &str) -> bool {
+    variables
+        .get_suggestion(tags, variable_name)
+        .and_then(|(_, opts)| opts.as_ref())
+        .is_some_and(|opts| opts.raw || opts.format.quote != Quote::None)
+}
+
+/// Asks whether an optional section of a snippet is included.
+fn prompt_optional_section(section: &str, previous: &VariableCache) -> Result<bool> {
+    // When prompting again, sections whose variables had values were included then
+    let names: Vec<String> = display::VAR_REGEX
+        .captures_iter(section)
+        .map(|caps| env_var::escape(&caps[1]))
+        .collect();
+    let included_before = !names.is_empty() && names.iter().all(|n| previous.contains_key(n));
+    let choices = if previous.is_empty() || included_before {
+        "include\nexclude"
+    } else {
+        "exclude\ninclude"
+    };
+
+    let opts = FinderOpts {
+        overrides: CONFIG.fzf_overrides_var(),
+        header: Some(format!("Optional: {}", section.trim())),
+        suggestion_type: SuggestionType::SingleSelection,
+        ..Default::default()
+    };
+
+    let (output, _) = crate::finder::call(opts, |stdin| {
+        stdin
+            .write_all(choices.as_bytes())
+            .context("Could not write to finder's stdin")?;
+        Ok(())
+    })
+    .context("Finder was unable to prompt for an optional section")?;
+
+    Ok(output.trim() == "include")
 }
 
 fn unique_result_count(results: &[&str]) -> usize {
@@ -90,19 +213,44 @@ fn unique_result_count(results: &[&str]) -> usize {
     vars.len()
 }
 
+/// Prompts for the variables of a snippet and replaces them.
+///
+/// Values already in `variable_cache` are reused, and new ones are added to it.
+/// The prompts of variables selected before start with their previous selection.
+/// Variables marked with `--foreach` are left as is, to be replaced by each of their values.
+/// With a `syntax`, values are escaped depending on where they're written.
+/// Optional sections are included or removed first, so that only the variables of included
+/// ones are prompted for.
 fn replace_variables_from_snippet(
     snippet: &str,
     tags: &str,
     variable_map: VariableMap,
     preview_context_env_vars: &EnvVars,
+    variable_cache: &mut VariableCache,
+    selections: &mut Selections,
+    syntax: Option<Syntax>,
 ) -> Result<String> {
-    let mut interpolated_snippet = String::from(snippet);
-    let mut variable_cache = VariableCache::new();
-
     if CONFIG.prevent_interpolation() {
-        return Ok(interpolated_snippet);
+        return Ok(String::from(snippet));
     }
 
+    let resolved = display::resolve_optional_sections(snippet, |section| {
+        prompt_optional_section(section, &selections.previous)
+    })?;
+
+    // The preview shows the snippet with the sections as chosen
+    let mut preview_env_vars = preview_context_env_vars.clone();
+    if preview_env_vars.get(env_var::PREVIEW_INITIAL_SNIPPET) == Some(&snippet.to_string()) {
+        preview_env_vars.insert(
+            env_var::PREVIEW_INITIAL_SNIPPET.to_string(),
+            resolved.clone(),
+        );
+    }
+    let preview_context_env_vars = &preview_env_vars;
+
+    let snippet = resolved.as_str();
+    let mut interpolated_snippet = String::from(snippet);
+
     // Find all variable references in the snippet (e.g., <variable_name>)
     let variable_references: Vec<&str> = display::VAR_REGEX
         .find_iter(snippet)
@@ -110,8 +258,17 @@ fn replace_variables_from_snippet(
         .collect();
     let variable_count = unique_result_count(&variable_references);
 
+    // Contexts are found in the snippet as written, as raw values may contain quotes
+    let contexts: Vec<quoting::Context> = display::VAR_REGEX
+        .find_iter(snippet)
+        .map(|m| match syntax {
+            Some(syntax) => quoting::context_after(&snippet[..m.start()], syntax),
+            None => quoting::Context::Unquoted,
+        })
+        .collect();
+
     // Process each variable reference
-    for variable_ref in variable_references {
+    for (variable_ref, context) in variable_references.into_iter().zip(contexts) {
         // Extract variable name from brackets: <name> -> name
         let variable_name = &variable_ref[1..variable_ref.len() - 1];
         let env_variable_name = env_var::escape(variable_name);
@@ -120,42 +277,60 @@ fn replace_variables_from_snippet(
         let value = if let Some(cached) = variable_cache.get(&env_variable_name) {
             // Use cached value if available
             cached.clone()
-        } else if let Some(suggestion) = variable_map.get_suggestion(tags, variable_name) {
-            // Process suggestion with nested variable replacement
-            let mut processed_suggestion = suggestion.clone();
-            processed_suggestion.0 = replace_variables_from_snippet(
-                &processed_suggestion.0,
-                tags,
-                variable_map.clone(),
-                preview_context_env_vars,
-            )?;
-
-            // Prompt user with the processed suggestion
-            prompt_finder(
-                variable_name,
-                Some(&processed_suggestion),
-                variable_count,
-                preview_context_env_vars,
-                &variable_cache,
-            )?
         } else {
-            // No suggestion available, prompt user directly
-            prompt_finder(
+            let suggestion = match variable_map.get_suggestion(tags, variable_name) {
+                // Process suggestion with nested variable replacement
+                Some(suggestion) => {
+                    let mut processed_suggestion = suggestion.clone();
+                    processed_suggestion.0 = replace_variables_from_snippet(
+                        &processed_suggestion.0,
+                        tags,
+                        variable_map.clone(),
+                        preview_context_env_vars,
+                        &mut VariableCache::new(),
+                        selections,
+                        None,
+                    )?;
+                    Some(processed_suggestion)
+                }
+                // No suggestion available, prompt user directly
+                None => None,
+            };
+
+            let (value, selection) = prompt_finder(
                 variable_name,
-                None,
+                suggestion.as_ref(),
                 variable_count,
                 preview_context_env_vars,
-                &variable_cache,
-            )?
+                variable_cache,
+                selections
+                    .previous
+                    .get(&env_variable_name)
+                    .map(String::as_str),
+            )?;
+            selections
+                .current
+                .insert(env_variable_name.clone(), selection);
+            value
         };
 
         // Cache the value for future references
         variable_cache.insert(env_variable_name, value.clone());
 
+        if foreach::is_foreach(&variable_map, tags, variable_name) {
+            continue;
+        }
+
         // Replace variable reference in snippet
         interpolated_snippet = if value.as_str() == "\n" {
             // Empty value - remove the variable reference entirely
             interpolated_snippet.replacen(variable_ref, "", 1)
+        } else if let Some(syntax) = syntax
+            && !is_raw(&variable_map, tags, variable_name)
+        {
+            let escaped = quoting::escape(&value, syntax, context)
+                .with_context(|| format!("Failed to quote the value of `{variable_name}`"))?;
+            interpolated_snippet.replacen(variable_ref, &escaped, 1)
         } else {
             interpolated_snippet.replacen(variable_ref, value.as_str(), 1)
         };
@@ -175,21 +350,15 @@ pub fn act(
     extractions: Result<(&str, Item)>,
     files: Vec<String>,
     variable_map: Option<VariableMap>,
+    previous_selections: VariableCache,
 ) -> Result<()> {
-    let (
-        key,
-        Item {
-            tags,
-            comment,
-            snippet,
-            file_index,
-            ..
-        },
-    ) = extractions?;
+    let (key, item) = extractions?;
 
     // Handle file editing shortcut
     if key == "ctrl-o" {
-        let file_idx = file_index.ok_or_else(|| anyhow!("No file index found"))?;
+        let file_idx = item
+            .file_index
+            .ok_or_else(|| anyhow!("No file index found"))?;
         edit::edit_file(Path::new(&files[file_idx]))
             .context("Could not open file in external editor")?;
         return Ok(());
@@ -199,22 +368,70 @@ pub fn act(
     let mut preview_context_env_vars = EnvVars::new();
     preview_context_env_vars.insert(
         env_var::PREVIEW_INITIAL_SNIPPET.to_string(),
-        snippet.clone(),
+        item.snippet.clone(),
     );
-    preview_context_env_vars.insert(env_var::PREVIEW_TAGS.to_string(), tags.clone());
-    preview_context_env_vars.insert(env_var::PREVIEW_COMMENT.to_string(), comment.to_string());
+    preview_context_env_vars.insert(env_var::PREVIEW_TAGS.to_string(), item.tags.clone());
+    preview_context_env_vars.insert(env_var::PREVIEW_COMMENT.to_string(), item.comment.clone());
 
     // Process snippet: replace variables, convert paths, handle newlines
+    let var_map = variable_map.ok_or_else(|| anyhow!("No variables received from finder"))?;
+    let mut variable_cache = VariableCache::new();
+    let mut selections = Selections {
+        previous: previous_selections,
+        ..Default::default()
+    };
+
+    // The working directory, environment and interpreter may have variables too,
+    // such as `psql <dsn>`, so they're interpolated as well
+    let mut interpolate = |text: &str, variables: &VariableMap, syntax| {
+        replace_variables_from_snippet(
+            text,
+            &item.tags,
+            variables.clone(),
+            &preview_context_env_vars,
+            &mut variable_cache,
+            &mut selections,
+            syntax,
+        )
+    };
+
+    // The interpreter comes first, as the suggestion commands of the other variables run with it
+    let interpreter = item
+        .shell
+        .as_ref()
+        .map(|shell| interpolate(shell, &var_map, None))
+        .transpose()
+        .context("Failed to replace variables from the interpreter")?;
+    let var_map = match &interpreter {
+        Some(interpreter) => var_map.with_shell(interpreter),
+        None => var_map,
+    };
+
+    let cwd = item
+        .cwd
+        .as_ref()
+        .map(|cwd| interpolate(cwd, &var_map, None))
+        .transpose()
+        .context("Failed to replace variables from the working directory")?;
+    let env = item
+        .env
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), interpolate(value, &var_map, None)?)))
+        .collect::<Result<Vec<_>>>()
+        .context("Failed to replace variables from the environment")?;
+
     let interpolated_snippet = {
-        let var_map = variable_map.ok_or_else(|| anyhow!("No variables received from finder"))?;
-        let mut s =
-            replace_variables_from_snippet(&snippet, &tags, var_map, &preview_context_env_vars)
-                .context("Failed to replace variables from snippet")?;
+        let syntax = quoting::snippet_syntax(interpreter.as_deref());
+        let mut s = interpolate(&item.snippet, &var_map, syntax)
+            .context("Failed to replace variables from snippet")?;
         s = with_absolute_path(s);
         s = display::with_new_lines(s);
         s
     };
 
+    let prepared = PreparedSnippet::new(interpolated_snippet, interpreter, cwd, env);
+    let runs = foreach::runs(&item, &var_map, &prepared, &variable_cache)?;
+
     // Handle command editing shortcut
     if key == "ctrl-e" {
         // Create a temporary file with the snippet
@@ -224,9 +441,18 @@ pub fn act(
             .tempfile()
             .context("Failed to create temporary file")?;
 
-        // Write the interpolated snippet to the temp file
+        // Write the command line to the temp file, once per value with `--foreach`, so the
+        // working directory, environment and interpreter of the snippet aren't lost
+        let snippets = match &runs {
+            Some((runs, _)) => runs
+                .iter()
+                .map(|run| run.prepared.command_line())
+                .collect::<Result<Vec<_>>>()?
+                .join("\n"),
+            None => prepared.command_line()?,
+        };
         temp_file
-            .write_all(interpolated_snippet.as_bytes())
+            .write_all(snippets.as_bytes())
             .context("Failed to write snippet to temporary file")?;
 
         // Get the path before the file is closed
@@ -240,30 +466,285 @@ pub fn act(
             std::fs::read_to_string(&temp_path).context("Failed to read edited snippet")?;
 
         // Output the edited snippet for the user to execute
-        println!("{}", edited_snippet.trim_end());
+        let edited_snippet = edited_snippet.trim_end();
+        println!("{edited_snippet}");
+
+        // Recorded like any printed snippet, as the command line the user ended up with
+        let edited = PreparedSnippet::new(edited_snippet.to_string(), None, None, vec![]);
+        record(
+            &item,
+            history::Action::Printed,
+            &edited,
+            &variable_cache,
+            &selections.current,
+        );
 
         return Ok(());
     }
 
+    match runs {
+        Some((runs, parallel)) => {
+            foreach::dispatch(key, &item, &runs, parallel, &selections.current)
+        }
+        None => dispatch(key, &item, &prepared, &variable_cache, &selections.current),
+    }
+}
+
+/// Prints, copies or executes a prepared snippet, as the configuration and `key` say,
+/// and records it in the history.
+pub fn dispatch(
+    key: &str,
+    item: &Item,
+    prepared: &PreparedSnippet,
+    bindings: &VariableCache,
+    selections: &VariableCache,
+) -> Result<()> {
+    let command_line = prepared.command_line()?;
+
     match CONFIG.action() {
         Action::Print => {
-            println!("{interpolated_snippet}");
+            println!("{command_line}");
+            record(
+                item,
+                history::Action::Printed,
+                prepared,
+                bindings,
+                selections,
+            );
         }
         Action::Execute => match key {
             "ctrl-y" => {
-                clipboard::copy(interpolated_snippet)?;
+                clipboard::copy(command_line)?;
+                record(
+                    item,
+                    history::Action::Copied,
+                    prepared,
+                    bindings,
+                    selections,
+                );
             }
             _ => {
-                let mut cmd = shell::out()?;
-                cmd.arg(&interpolated_snippet[..]);
-                debug!(cmd = ?cmd);
-                cmd.spawn()
-                    .map_err(|e| ShellSpawnError::new(&interpolated_snippet[..], e))?
-                    .wait()
-                    .context("bash was not running")?;
+                if let Some(message) = required_confirmation(item, [(prepared, &*command_line)])?
+                    && !ask_for_confirmation(&message, &command_line)?
+                {
+                    eprintln!("The command was not executed.");
+                    return Ok(());
+                }
+                execute_and_record(item, prepared, bindings, selections)?
             }
         },
     };
 
     Ok(())
 }
+
+/// Adds a snippet to the history, with the values its variables were bound to
+/// and the lines selected for them.
+pub fn record(
+    item: &Item,
+    action: history::Action,
+    prepared: &PreparedSnippet,
+    bindings: &VariableCache,
+    selections: &VariableCache,
+) {
+    let entry = history::Entry::new(item, action, prepared, bindings)
+        .map(|entry| entry.with_selections(selections));
+    history::record_or_warn(entry);
+}
+
+/// Executes a snippet and adds it to the history, along with its exit status and duration.
+///
+/// Fails with [`ExecutionFailed`] if the snippet didn't succeed.
+pub fn execute_and_record(
+    item: &Item,
+    prepared: &PreparedSnippet,
+    bindings: &VariableCache,
+    selections: &VariableCache,
+) -> Result<()> {
+    let start = Instant::now();
+    let status = prepared.execute()?;
+    let entry =
+        history::Entry::new(item, history::Action::Executed, prepared, bindings).map(|entry| {
+            entry
+                .with_selections(selections)
+                .with_outcome(status, start.elapsed())
+        });
+    history::record_or_warn(entry);
+
+    ensure_success(status)
+}
+
+/// The confirmation the runs of a snippet require before they're executed, because of the
+/// execution policy or else of the `; confirm` metacomment of the snippet. Fails if the policy
+/// denies one of them.
+pub fn required_confirmation<'a>(
+    item: &Item,
+    runs: impl IntoIterator<Item = (&'a PreparedSnippet, &'a str)>,
+) -> Result<Option<String>> {
+    let mut confirm = item.confirm.clone();
+    for (prepared, command_line) in runs {
+        if let Verdict::Confirm(message) = policy::check(&prepared.snippet, command_line)? {
+            confirm = Some(message);
+        }
+    }
+    Ok(confirm)
+}
+
+/// Shows the fully interpolated command and asks the user to explicitly pick `Yes`.
+pub fn ask_for_confirmation(message: &str, interpolated_snippet: &str) -> Result<bool> {
+    let opts = FinderOpts {
+        header: Some(format!(
+            "{message}\n\n{interpolated_snippet}\n\nDo you want to execute this command?"
+        )),
+        suggestion_type: SuggestionType::SingleSelection,
+        ..Default::default()
+    };
+
+    let (response, _) = crate::finder::call(opts, |stdin| {
+        stdin
+            .write_all(b"No\nYes")
+            .context("Unable to write alternatives")?;
+        Ok(())
+    })
+    .context("Unable to get confirmation")?;
+
+    Ok(response == "Yes")
+}
+
+/// A snippet whose variables are replaced, along with how it runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedSnippet {
+    pub snippet: String,
+    /// Program the snippet runs with, instead of the configured shell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<(String, String)>,
+}
+
+impl PreparedSnippet {
+    pub fn new(
+        snippet: String,
+        interpreter: Option<String>,
+        cwd: Option<String>,
+        env: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            snippet,
+            interpreter,
+            cwd: cwd.map(|cwd| fs::expand_home(&cwd)),
+            env,
+        }
+    }
+
+    /// The shell command line equivalent to running the snippet, as printed, copied
+    /// and checked against the execution policy along with the snippet itself.
+    pub fn command_line(&self) -> Result<String> {
+        let (command_line, export) = match &self.interpreter {
+            Some(interpreter) => (
+                Interpreter::new(interpreter)?.command_line(&self.snippet),
+                false,
+            ),
+            None => {
+                let several_commands = self.snippet.contains(['\n', ';', '&', '|']);
+                let expands_env = self.env.iter().any(|(key, _)| {
+                    self.snippet.contains(&format!("${key}"))
+                        || self.snippet.contains(&format!("${{{key}"))
+                });
+                (self.snippet.clone(), several_commands || expands_env)
+            }
+        };
+
+        Ok(shell::with_environment(
+            &command_line,
+            export,
+            self.cwd.as_deref(),
+            &self.env,
+        ))
+    }
+
+    /// Replaces a variable left in the snippet, its interpreter, working directory
+    /// and environment. With a `syntax`, the value is escaped where it's written in the snippet.
+    pub fn with_value(
+        &self,
+        variable_name: &str,
+        value: &str,
+        syntax: Option<Syntax>,
+    ) -> Result<Self> {
+        let variable_ref = format!("<{variable_name}>");
+        let replace = |text: &str| text.replace(&variable_ref, value);
+
+        Ok(Self {
+            snippet: match syntax {
+                Some(syntax) => quoting::replace(&self.snippet, &variable_ref, value, syntax)?,
+                None => replace(&self.snippet),
+            },
+            interpreter: self.interpreter.as_deref().map(replace),
+            cwd: self.cwd.as_deref().map(replace),
+            env: self
+                .env
+                .iter()
+                .map(|(key, v)| (key.clone(), replace(v)))
+                .collect(),
+        })
+    }
+
+    /// Spawns the snippet with its interpreter, or else the configured shell.
+    ///
+    /// `configure` sets the command up before it's spawned, e.g. to capture its output.
+    pub fn spawn(&self, configure: impl FnOnce(&mut Command)) -> Result<Child> {
+        if let Some(cwd) = &self.cwd
+            && !Path::new(cwd).is_dir()
+        {
+            return Err(anyhow!("The working directory `{cwd}` doesn't exist"));
+        }
+
+        shell::spawn_script(self.interpreter.as_deref(), &self.snippet, |cmd| {
+            if let Some(cwd) = &self.cwd {
+                cmd.current_dir(cwd);
+            }
+            cmd.envs(self.env.iter().map(|(key, value)| (key, value)));
+            configure(cmd);
+        })
+    }
+
+    /// Runs the snippet and waits for it to exit.
+    pub fn execute(&self) -> Result<ExitStatus> {
+        self.spawn(|_| {})?.wait().context("bash was not running")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::terminal::DELIMITER;
+
+    #[test]
+    fn test_query_of() {
+        assert_eq!(query_of("main\n"), "main");
+        assert_eq!(
+            query_of("root  42  /usr/bin/navi"),
+            "root  42  /usr/bin/navi"
+        );
+        // JSON suggestions are selected again by their displayed text
+        let line = format!("web-1{DELIMITER}{{\"name\":\"web-1\"}}\n");
+        assert_eq!(query_of(&line), "web-1");
+    }
+
+    #[test]
+    fn test_ensure_success() {
+        let status = |snippet: &str| {
+            PreparedSnippet::new(snippet.to_string(), None, None, vec![])
+                .execute()
+                .unwrap()
+        };
+
+        assert!(ensure_success(status("true")).is_ok());
+        let err = ensure_success(status("exit 3")).unwrap_err();
+        let failed = err.downcast_ref::<ExecutionFailed>().unwrap();
+        assert_eq!(failed.exit_code(), 3);
+        assert_eq!(ExecutionFailed::RunsFailed(2).exit_code(), 1);
+    }
+}
@@ -0,0 +1,413 @@
+//! Runs a snippet once per value of its `--foreach` variable.
+
+use super::actor::{self, ExecutionFailed, PreparedSnippet};
+use crate::common::clipboard;
+use crate::common::quoting;
+use crate::common::types::VariableCache;
+use crate::config::Action;
+use crate::display;
+use crate::env_var;
+use crate::history;
+use crate::prelude::*;
+use crate::structures::cheat::VariableMap;
+use crate::structures::item::Item;
+use std::io::{BufRead, BufReader, Read};
+use std::process::ExitStatus;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The snippet, as it runs with one of the values.
+pub struct Run {
+    pub value: String,
+    pub prepared: PreparedSnippet,
+    pub bindings: VariableCache,
+}
+
+pub fn is_foreach(variables: &VariableMap, tags: &str, variable_name: &str) -> bool {
+    variables
+        .get_suggestion(tags, variable_name)
+        .and_then(|(_, opts)| opts.as_ref())
+        .is_some_and(|opts| opts.foreach)
+}
+
+/// Expands a snippet whose `--foreach` variable was left as is, along with how many
+/// of the runs may happen at the same time. Snippets without such a variable give `None`.
+pub fn runs(
+    item: &Item,
+    variables: &VariableMap,
+    prepared: &PreparedSnippet,
+    bindings: &VariableCache,
+) -> Result<Option<(Vec<Run>, usize)>> {
+    // Variables of excluded optional sections are no longer there
+    let texts = prepared
+        .cwd
+        .iter()
+        .chain(prepared.env.iter().map(|(_, value)| value))
+        .chain(prepared.interpreter.iter())
+        .chain([&prepared.snippet]);
+
+    let mut names: Vec<&str> = texts
+        .flat_map(|text| display::VAR_REGEX.captures_iter(text))
+        .filter_map(|caps| caps.get(1))
+        .map(|name| name.as_str())
+        .filter(|name| is_foreach(variables, &item.tags, name))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let name = match names.as_slice() {
+        [] => return Ok(None),
+        [name] => *name,
+        _ => {
+            return Err(anyhow!(
+                "Only one variable of a snippet can use `--foreach`, not {}",
+                names.join(", ")
+            ));
+        }
+    };
+
+    let parallel = variables
+        .get_suggestion(&item.tags, name)
+        .and_then(|(_, opts)| opts.as_ref())
+        .map_or(1, |opts| opts.parallel);
+
+    let env_variable_name = env_var::escape(name);
+    let values = bindings
+        .get(&env_variable_name)
+        .map(String::as_str)
+        .unwrap_or_default();
+
+    let syntax = if actor::is_raw(variables, &item.tags, name) {
+        None
+    } else {
+        quoting::snippet_syntax(prepared.interpreter.as_deref())
+    };
+
+    let runs = values
+        .lines()
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            let mut bindings = bindings.clone();
+            bindings.insert(env_variable_name.clone(), value.to_string());
+            Ok(Run {
+                value: value.to_string(),
+                prepared: prepared.with_value(name, value, syntax)?,
+                bindings,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Some((runs, parallel)))
+}
+
+/// Prints, copies or executes every run, like [`actor::dispatch`] does for a single snippet.
+pub fn dispatch(
+    key: &str,
+    item: &Item,
+    runs: &[Run],
+    parallel: usize,
+    selections: &VariableCache,
+) -> Result<()> {
+    if runs.is_empty() {
+        eprintln!("No value was selected, so nothing was executed.");
+        return Ok(());
+    }
+
+    let command_lines = runs
+        .iter()
+        .map(|run| run.prepared.command_line())
+        .collect::<Result<Vec<_>>>()?;
+    let all_command_lines = command_lines.join("\n");
+
+    let record_all = |action| {
+        for run in runs {
+            actor::record(item, action, &run.prepared, &run.bindings, selections);
+        }
+    };
+
+    match CONFIG.action() {
+        Action::Print => {
+            println!("{all_command_lines}");
+            record_all(history::Action::Printed);
+        }
+        Action::Execute if key == "ctrl-y" => {
+            clipboard::copy(all_command_lines)?;
+            record_all(history::Action::Copied);
+        }
+        Action::Execute => {
+            let checked = runs
+                .iter()
+                .zip(&command_lines)
+                .map(|(run, command_line)| (&run.prepared, command_line.as_str()));
+            if let Some(message) = actor::required_confirmation(item, checked)?
+                && !actor::ask_for_confirmation(&message, &all_command_lines)?
+            {
+                eprintln!("The commands were not executed.");
+                return Ok(());
+            }
+            execute(item, runs, parallel, selections)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the lines of a child's output, prefixed with the value it runs with.
+fn forward_lines(reader: impl Read, prefix: &str, to_stderr: bool) {
+    let mut reader = BufReader::new(reader);
+    let mut line = vec![];
+
+    while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        if to_stderr {
+            eprintln!("{prefix}{text}");
+        } else {
+            println!("{prefix}{text}");
+        }
+        line.clear();
+    }
+}
+
+fn execute_one(run: &Run, inherit_stdin: bool) -> Result<(ExitStatus, Duration)> {
+    let start = Instant::now();
+
+    let mut child = run.prepared.spawn(|cmd| {
+        if !inherit_stdin {
+            cmd.stdin(Stdio::null());
+        }
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    })?;
+
+    let prefix = format!("[{}] ", run.value);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    thread::scope(|s| {
+        if let Some(stdout) = stdout {
+            s.spawn(|| forward_lines(stdout, &prefix, false));
+        }
+        if let Some(stderr) = stderr {
+            s.spawn(|| forward_lines(stderr, &prefix, true));
+        }
+    });
+
+    let status = child.wait().context("bash was not running")?;
+    Ok((status, start.elapsed()))
+}
+
+type Outcome = Option<Result<ExitStatus>>;
+
+/// Executes the runs, `parallel` at a time, calling `done` as soon as each of them ends.
+/// Runs which couldn't be spawned have an error as outcome.
+fn execute_all(
+    runs: &[Run],
+    parallel: usize,
+    done: impl Fn(&Run, ExitStatus, Duration) + Sync,
+) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Outcome>> = Mutex::new(runs.iter().map(|_| None).collect());
+
+    // Runs can only read from the terminal when they don't compete for it
+    let inherit_stdin = parallel == 1;
+
+    thread::scope(|s| {
+        for _ in 0..parallel.min(runs.len()) {
+            s.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(run) = runs.get(i) else {
+                        break;
+                    };
+
+                    let outcome = execute_one(run, inherit_stdin);
+                    if let Ok((status, duration)) = &outcome {
+                        done(run, *status, *duration);
+                    }
+
+                    outcomes.lock().expect("poisoned lock")[i] =
+                        Some(outcome.map(|(status, _)| status));
+                }
+            });
+        }
+    });
+
+    outcomes.into_inner().expect("poisoned lock")
+}
+
+/// Executes the runs, records them, then sums their exit statuses up.
+///
+/// Like a shell loop would, fails with [`ExecutionFailed::RunsFailed`] if any of them did.
+fn execute(item: &Item, runs: &[Run], parallel: usize, selections: &VariableCache) -> Result<()> {
+    let outcomes = execute_all(runs, parallel, |run, status, duration| {
+        let entry = history::Entry::new(
+            item,
+            history::Action::Executed,
+            &run.prepared,
+            &run.bindings,
+        )
+        .map(|entry| {
+            entry
+                .with_selections(selections)
+                .with_outcome(status, duration)
+        });
+        history::record_or_warn(entry);
+    });
+
+    let failures = failures(runs, &outcomes);
+    print_summary(runs.len(), &failures);
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ExecutionFailed::RunsFailed(failures.len()).into())
+    }
+}
+
+/// The runs which didn't succeed, with the reason why.
+fn failures(runs: &[Run], outcomes: &[Outcome]) -> Vec<String> {
+    runs.iter()
+        .zip(outcomes)
+        .filter_map(|(run, outcome)| {
+            let reason = match outcome {
+                Some(Ok(status)) if status.success() => return None,
+                Some(Ok(status)) => match status.code() {
+                    Some(code) => format!("exit status {code}"),
+                    None => "killed by a signal".to_string(),
+                },
+                Some(Err(e)) => format!("{e:#}"),
+                None => "not executed".to_string(),
+            };
+            Some(format!("  [{}] {reason}", run.value))
+        })
+        .collect()
+}
+
+fn print_summary(runs: usize, failures: &[String]) {
+    eprintln!("\n{} of {} runs succeeded", runs - failures.len(), runs);
+    for failure in failures {
+        eprintln!("{failure}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finder::structures::Opts;
+
+    fn variables(parallel: usize) -> VariableMap {
+        let mut variables = VariableMap::default();
+        let opts = Opts {
+            foreach: true,
+            parallel,
+            ..Default::default()
+        };
+        variables.insert_suggestion("ssh", "host", ("cat hosts".to_string(), Some(opts)));
+        variables
+    }
+
+    fn run(value: &str, snippet: &str, cwd: Option<&str>) -> Run {
+        Run {
+            value: value.to_string(),
+            prepared: PreparedSnippet::new(
+                snippet.to_string(),
+                None,
+                cwd.map(str::to_string),
+                vec![],
+            ),
+            bindings: VariableCache::new(),
+        }
+    }
+
+    #[test]
+    fn test_runs() {
+        let item = Item {
+            tags: "ssh".to_string(),
+            snippet: "ssh <host> uptime".to_string(),
+            ..Default::default()
+        };
+        let prepared = PreparedSnippet::new(
+            "ssh <host> uptime".to_string(),
+            None,
+            None,
+            vec![("HOST".to_string(), "<host>".to_string())],
+        );
+        let bindings = VariableCache::from([
+            ("host".to_string(), "web-1\n\nweb 2\n".to_string()),
+            ("user".to_string(), "admin".to_string()),
+        ]);
+
+        let (runs, parallel) = runs(&item, &variables(3), &prepared, &bindings)
+            .unwrap()
+            .unwrap();
+        assert_eq!(parallel, 3);
+        let values: Vec<_> = runs.iter().map(|run| run.value.as_str()).collect();
+        assert_eq!(values, ["web-1", "web 2"]);
+        assert_eq!(runs[0].prepared.snippet, "ssh web-1 uptime");
+        // Values are only quoted with `shell.quote_values`
+        assert_eq!(runs[1].prepared.snippet, "ssh web 2 uptime");
+        assert_eq!(runs[1].prepared.env[0].1, "web 2");
+        assert_eq!(runs[1].bindings["host"], "web 2");
+        assert_eq!(runs[1].bindings["user"], "admin");
+
+        // Snippets without a `--foreach` variable run once
+        let once = PreparedSnippet::new("uptime".to_string(), None, None, vec![]);
+        assert!(runs_of(&item, &once).is_none());
+    }
+
+    fn runs_of(item: &Item, prepared: &PreparedSnippet) -> Option<(Vec<Run>, usize)> {
+        runs(item, &variables(1), prepared, &VariableCache::new()).unwrap()
+    }
+
+    #[test]
+    fn test_with_value() {
+        let prepared = PreparedSnippet::new(
+            "echo <host> \"at <host>\"".to_string(),
+            Some("bash --login".to_string()),
+            Some("/srv/<host>".to_string()),
+            vec![("HOST".to_string(), "<host>".to_string())],
+        );
+
+        let quoted = prepared
+            .with_value("host", "it's", Some(quoting::Syntax::Posix))
+            .unwrap();
+        assert_eq!(quoted.snippet, r#"echo 'it'\''s' "at it's""#);
+        assert_eq!(quoted.cwd.as_deref(), Some("/srv/it's"));
+        assert_eq!(quoted.env[0].1, "it's");
+        assert_eq!(quoted.interpreter.as_deref(), Some("bash --login"));
+
+        let raw = prepared.with_value("host", "it's", None).unwrap();
+        assert_eq!(raw.snippet, r#"echo it's "at it's""#);
+    }
+
+    #[test]
+    fn test_execute_all() {
+        let runs = [
+            run("a", "sleep 0.5", None),
+            run("b", "sleep 0.5; exit 3", None),
+            run("c", "sleep 0.5", None),
+            run("d", "true", Some("/nonexistent/navi")),
+        ];
+
+        let done = AtomicUsize::new(0);
+        let start = Instant::now();
+        let outcomes = execute_all(&runs, 4, |_, _, _| {
+            done.fetch_add(1, Ordering::SeqCst);
+        });
+
+        // The runs happened at the same time
+        assert!(start.elapsed() < Duration::from_millis(1400));
+        assert_eq!(done.into_inner(), 3);
+        assert!(outcomes[0].as_ref().unwrap().as_ref().unwrap().success());
+        assert!(matches!(outcomes[3], Some(Err(_))));
+
+        let failures = failures(&runs, &outcomes);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0], "  [b] exit status 3");
+        assert!(failures[1].starts_with("  [d] "));
+
+        // One at a time, every run still happens
+        let outcomes = execute_all(&runs[..2], 1, |_, _, _| {});
+        assert_eq!(self::failures(&runs[..2], &outcomes).len(), 1);
+    }
+}
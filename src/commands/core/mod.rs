@@ -1,24 +1,38 @@
-mod actor;
+pub mod actor;
+mod foreach;
 mod preview;
+mod provider;
 mod suggestion;
 
+use crate::common::types::VariableCache;
 use crate::config::Source;
 use crate::display;
 use crate::filesystem;
 use crate::finder::structures::Opts as FinderOpts;
 use crate::parser::Parser;
 use crate::prelude::*;
+use crate::structures::cheat::VariableMap;
 use crate::structures::fetcher::Fetcher;
+use crate::structures::item::Item;
+use crate::tag_rules::TagRules;
 use crate::welcome;
+use std::io;
 
 pub fn init(fetcher: Box<dyn Fetcher>) -> Result<()> {
     let config = &CONFIG;
     let opts = FinderOpts::snippet_default();
     debug!("opts = {opts:#?}");
+    let tag_rules = CONFIG
+        .tag_rules()
+        .map(|r| TagRules::parse(&r))
+        .transpose()?;
     // let fetcher = config.fetcher();
 
     let (raw_selection, (variables, files)) = crate::finder::call(opts, |writer| {
         let mut parser = Parser::new(writer, true);
+        if let Some(tag_rules) = &tag_rules {
+            parser.set_tag_rules(tag_rules.clone());
+        }
 
         let found_something = fetcher
             .fetch(&mut parser)
@@ -39,7 +53,7 @@ pub fn init(fetcher: Box<dyn Fetcher>) -> Result<()> {
         return init(fetcher);
     }
 
-    actor::act(extractions, files, variables)?;
+    actor::act(extractions, files, variables, VariableCache::new())?;
 
     Ok(())
 }
@@ -59,6 +73,26 @@ pub fn get_fetcher() -> Result<Box<dyn Fetcher>> {
     }
 }
 
+/// Reads every snippet the finder would be offered, without calling the finder.
+///
+/// Returns the snippets, their variables and the cheatsheet files they were read from.
+pub fn fetch_items(tag_rules: Option<&str>) -> Result<(Vec<Item>, VariableMap, Vec<String>)> {
+    let fetcher = get_fetcher()?;
+
+    let mut sink = io::sink();
+    let mut parser = Parser::new(&mut sink, false);
+    parser.keep_items();
+    if let Some(tr) = tag_rules {
+        parser.set_tag_rules(TagRules::parse(tr)?);
+    }
+
+    fetcher
+        .fetch(&mut parser)
+        .context("Failed to parse variables intended for finder")?;
+
+    Ok((parser.take_items(), parser.variables, fetcher.files()))
+}
+
 pub fn main() -> Result<()> {
     let fetcher = get_fetcher()?;
     init(fetcher)
@@ -27,8 +27,12 @@ pub fn build_preview_command(
     }
 }
 
-pub fn calculate_preview_window(extra_preview: Option<&String>, variable_count: usize) -> String {
-    if extra_preview.is_none() {
+pub fn calculate_preview_window(
+    extra_preview: Option<&String>,
+    variable_count: usize,
+    is_json: bool,
+) -> String {
+    if extra_preview.is_none() && !is_json {
         format!(
             "{}:{}",
             constants::DEFAULT_PREVIEW_DIRECTION,
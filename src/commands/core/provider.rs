@@ -0,0 +1,168 @@
+//! Built-in suggestion providers, such as `$ file: @files *.rs`, which run in-process
+//! instead of with a shell.
+
+use crate::common::fs;
+use crate::common::shell::ShellSpawnError;
+use crate::parser::matches_glob;
+use crate::prelude::*;
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Whether a suggestion command uses a built-in provider.
+pub fn is_builtin(command: &str) -> bool {
+    command.trim_start().starts_with('@')
+}
+
+/// Lists the suggestions of a provider, one per line.
+pub fn suggestions(command: &str) -> Result<String> {
+    let words = shellwords::split(command.trim())
+        .with_context(|| format!("Failed to parse the provider `{}`", command.trim()))?;
+    let (name, args) = words
+        .split_first()
+        .ok_or_else(|| anyhow!("Provider is empty"))?;
+
+    let lines = match name.as_str() {
+        "@files" => walk(args, false),
+        "@dirs" => walk(args, true),
+        "@git-branches" => git(&["branch", "--format=%(refname:short)"])?,
+        "@git-remotes" => git(&["remote"])?,
+        "@env-vars" => {
+            let mut names: Vec<String> = std::env::vars_os()
+                .map(|(name, _)| name.to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        }
+        "@hosts" => hosts(),
+        "@processes" => processes()?,
+        _ => return Err(anyhow!("Unknown provider `{name}`")),
+    };
+
+    Ok(lines.into_iter().map(|line| line + "\n").collect())
+}
+
+/// Files or directories under the current one, matching any of the `patterns` if there are some.
+///
+/// Patterns with a `/` match the whole path, and others only its last component.
+fn walk(patterns: &[String], dirs: bool) -> Vec<String> {
+    WalkDir::new(".")
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir() == dirs)
+        .filter_map(|entry| {
+            let path = entry.path().strip_prefix(".").ok()?.display().to_string();
+            let name = entry.file_name().to_string_lossy();
+            let matches = patterns.is_empty()
+                || patterns.iter().any(|pattern| {
+                    let text = if pattern.contains('/') { &path } else { &*name };
+                    matches_glob(text, pattern.trim_start_matches("./"))
+                });
+            matches.then_some(path)
+        })
+        .collect()
+}
+
+fn git(args: &[&str]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| ShellSpawnError::new(format!("git {}", args.join(" ")), e))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Host names of an ssh config, without patterns such as `*.internal`.
+fn ssh_config_hosts(config: &str) -> Vec<String> {
+    config
+        .lines()
+        .filter_map(|line| {
+            let (keyword, value) = line.trim().split_once(char::is_whitespace)?;
+            keyword.eq_ignore_ascii_case("host").then_some(value)
+        })
+        .flat_map(str::split_whitespace)
+        .filter(|host| !host.contains(['*', '?', '!']))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Host names of an `/etc/hosts` file.
+fn etc_hosts(hosts: &str) -> Vec<String> {
+    hosts
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split_whitespace().skip(1))
+        .map(str::to_string)
+        .collect()
+}
+
+fn hosts() -> Vec<String> {
+    let read = |path: &str| std::fs::read_to_string(fs::expand_home(path)).unwrap_or_default();
+
+    let mut hosts = ssh_config_hosts(&read("~/.ssh/config"));
+    for host in etc_hosts(&read("/etc/hosts")) {
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
+/// Running processes as `<pid>  <name>`, so that `--column` can pick either.
+fn processes() -> Result<Vec<String>> {
+    if let Ok(entries) = std::fs::read_dir("/proc") {
+        let mut processes: Vec<(u32, String)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+                let name = std::fs::read_to_string(entry.path().join("comm")).ok()?;
+                Some((pid, name.trim_end().to_string()))
+            })
+            .collect();
+        processes.sort();
+        return Ok(processes
+            .into_iter()
+            .map(|(pid, name)| format!("{pid}  {name}"))
+            .collect());
+    }
+
+    // Systems without procfs, such as macOS
+    let output = Command::new("ps")
+        .args(["-axo", "pid=,comm="])
+        .output()
+        .map_err(|e| ShellSpawnError::new("ps", e))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, name) = line.trim().split_once(char::is_whitespace)?;
+            Some(format!("{pid}  {}", name.trim()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosts() {
+        let config = "Host web-1 web-2\n  HostName 10.0.0.1\nhost *.internal !bastion\nHost db\n";
+        assert_eq!(ssh_config_hosts(config), ["web-1", "web-2", "db"]);
+
+        let hosts = "# comment\n127.0.0.1 localhost\n10.0.0.2  api api.local # staging\n";
+        assert_eq!(etc_hosts(hosts), ["localhost", "api", "api.local"]);
+    }
+
+    #[test]
+    fn test_unknown_provider() {
+        assert!(suggestions("@nope").is_err());
+    }
+}
@@ -1,19 +1,26 @@
 /// Helper module for executing suggestion commands
-use crate::common::shell::{self, ShellSpawnError};
+use super::provider;
+use crate::common::shell;
 use crate::common::types::VariableCache;
 use crate::env_var;
-use crate::finder::structures::Opts as FinderOpts;
+use crate::finder::structures::{self, Opts as FinderOpts};
 use crate::prelude::*;
 use std::process::Stdio;
 
 /// Execute a suggestion command and return the output
-pub fn execute_suggestion_command(command: &str, variable_cache: &VariableCache) -> Result<String> {
-    let mut cmd = shell::out()?;
-    cmd.stdout(Stdio::piped()).arg(command).envs(variable_cache);
-
-    debug!(cmd = ?cmd);
+pub fn execute_suggestion_command(
+    command: &str,
+    interpreter: Option<&str>,
+    variable_cache: &VariableCache,
+) -> Result<String> {
+    if provider::is_builtin(command) {
+        return provider::suggestions(command);
+    }
 
-    let child = cmd.spawn().map_err(|e| ShellSpawnError::new(command, e))?;
+    // Unlike shells, interpreters such as python care about leading whitespace
+    let child = shell::spawn_script(interpreter, command.trim(), |cmd| {
+        cmd.stdout(Stdio::piped()).envs(variable_cache);
+    })?;
 
     let output = child
         .wait_with_output()
@@ -29,8 +36,15 @@ pub fn apply_suggestion_options(
 ) -> Option<String> {
     let mut extra_preview = None;
 
-    if let Some(column) = options.column {
-        preview_env_vars.insert(env_var::PREVIEW_COLUMN.to_string(), column.to_string());
+    if let Some(ref columns) = options.column {
+        preview_env_vars.insert(
+            env_var::PREVIEW_COLUMN.to_string(),
+            structures::format_columns(columns),
+        );
+    }
+
+    if let Some(ref join) = options.column_join {
+        preview_env_vars.insert(env_var::PREVIEW_COLUMN_JOIN.to_string(), join.clone());
     }
 
     if let Some(ref delimiter) = options.delimiter {
@@ -41,6 +55,30 @@ pub fn apply_suggestion_options(
         preview_env_vars.insert(env_var::PREVIEW_MAP.to_string(), map.clone());
     }
 
+    if let Some(ref shell) = options.shell {
+        preview_env_vars.insert(env_var::PREVIEW_SHELL.to_string(), shell.clone());
+    }
+
+    if let Some(ref json) = options.json {
+        let path = json.value.clone().unwrap_or_else(|| ".".to_string());
+        preview_env_vars.insert(env_var::PREVIEW_JSON_VALUE.to_string(), path);
+    }
+
+    let format = &options.format;
+    if let Some(quote) = format.quote.name() {
+        preview_env_vars.insert(env_var::PREVIEW_QUOTE.to_string(), quote.to_string());
+    }
+
+    for (name, value) in [
+        (env_var::PREVIEW_PREFIX, &format.prefix),
+        (env_var::PREVIEW_SUFFIX, &format.suffix),
+        (env_var::PREVIEW_JOIN, &format.join),
+    ] {
+        if let Some(value) = value {
+            preview_env_vars.insert(name.to_string(), value.clone());
+        }
+    }
+
     if let Some(ref preview) = options.preview {
         extra_preview = Some(preview.clone());
     }
@@ -0,0 +1,379 @@
+use crate::commands::core::fetch_items;
+use crate::common::{clipboard, fs};
+use crate::filesystem;
+use crate::finder::{self, MIN_FZF_VERSION_MAJOR, MIN_FZF_VERSION_MINOR, MIN_FZF_VERSION_PATCH};
+use crate::prelude::*;
+use clap::Args;
+use crossterm::style::Stylize;
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Args)]
+pub struct Input {
+    /// Prints the checks as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: String,
+    status: Status,
+    message: String,
+}
+
+fn check(name: impl Into<String>, status: Status, message: impl Into<String>) -> Check {
+    Check {
+        name: name.into(),
+        status,
+        message: message.into(),
+    }
+}
+
+fn check_config() -> Check {
+    if let Some(e) = CONFIG.load_error() {
+        return check("config", Status::Fail, e);
+    }
+
+    let profile = CONFIG
+        .profile()
+        .map(|p| format!(", with the `{p}` profile"))
+        .unwrap_or_default();
+
+    match CONFIG.files() {
+        [] => check(
+            "config",
+            Status::Pass,
+            format!("no configuration file, navi uses its defaults{profile}"),
+        ),
+        files => check(
+            "config",
+            Status::Pass,
+            format!("{}{profile}", files.join(", ")),
+        ),
+    }
+}
+
+fn check_fzf() -> Check {
+    let Some(path) = fs::which("fzf") else {
+        return check(
+            "fzf",
+            Status::Fail,
+            "fzf isn't installed or isn't in the PATH",
+        );
+    };
+
+    let min = (
+        MIN_FZF_VERSION_MAJOR,
+        MIN_FZF_VERSION_MINOR,
+        MIN_FZF_VERSION_PATCH,
+    );
+
+    match finder::check_fzf_version() {
+        None => check(
+            "fzf",
+            Status::Warn,
+            format!("couldn't read the version of {}", path.display()),
+        ),
+        Some(version) if version < min => check(
+            "fzf",
+            Status::Fail,
+            format!(
+                "version {}.{}.{} of {} is older than {}.{}.{}",
+                version.0,
+                version.1,
+                version.2,
+                path.display(),
+                min.0,
+                min.1,
+                min.2
+            ),
+        ),
+        Some((major, minor, patch)) => check(
+            "fzf",
+            Status::Pass,
+            format!("version {major}.{minor}.{patch} at {}", path.display()),
+        ),
+    }
+}
+
+/// Checks that the program of a shell command, such as `bash --norc`, can be found.
+fn check_shell(name: &str, key: &str, command: &str) -> Check {
+    let program = shellwords::split(command)
+        .ok()
+        .and_then(|words| words.into_iter().next());
+
+    let Some(program) = program else {
+        return check(name, Status::Fail, format!("`{key}` is empty or invalid"));
+    };
+
+    let path = Path::new(&program);
+    let found = if path.components().count() > 1 {
+        path.exists().then(|| path.to_path_buf())
+    } else {
+        fs::which(&program)
+    };
+
+    match found {
+        Some(path) => check(
+            name,
+            Status::Pass,
+            format!("`{program}` is {}", path.display()),
+        ),
+        None => check(
+            name,
+            Status::Fail,
+            format!("`{program}`, set by `{key}`, can't be found"),
+        ),
+    }
+}
+
+fn check_clipboard() -> Check {
+    let found: Vec<_> = clipboard::TOOLS
+        .into_iter()
+        .filter(|tool| fs::which(tool).is_some())
+        .collect();
+
+    match found.first() {
+        Some(tool) => check("clipboard", Status::Pass, format!("copying with `{tool}`")),
+        None => check(
+            "clipboard",
+            Status::Warn,
+            format!(
+                "none of {} is installed, so snippets can't be copied",
+                clipboard::TOOLS.join(", ")
+            ),
+        ),
+    }
+}
+
+fn check_cheats() -> Vec<Check> {
+    let folders = match filesystem::cheat_folders(CONFIG.path()) {
+        Ok(folders) => folders,
+        Err(e) => return vec![check("cheats", Status::Fail, format!("{e:#}"))],
+    };
+
+    let mut checks: Vec<_> = folders
+        .iter()
+        .map(|folder| {
+            let name = format!("cheats: {}", folder.display());
+            if !folder.exists() {
+                return check(name, Status::Fail, "the path doesn't exist");
+            }
+            match filesystem::all_cheat_files(folder).len() {
+                0 => check(name, Status::Warn, "no .cheat file"),
+                1 => check(name, Status::Pass, "1 .cheat file"),
+                n => check(name, Status::Pass, format!("{n} .cheat files")),
+            }
+        })
+        .collect();
+
+    let local_files = filesystem::current_local_cheat_files();
+    if !local_files.trusted.is_empty() {
+        checks.push(check(
+            "cheats: project",
+            Status::Pass,
+            format!(
+                "{} .cheat files in the current project",
+                local_files.trusted.len()
+            ),
+        ));
+    }
+    if !local_files.untrusted.is_empty() {
+        checks.push(check(
+            "cheats: project",
+            Status::Warn,
+            format!(
+                "{} .cheat files skipped, as the project isn't listed in `project.trusted`",
+                local_files.untrusted.len()
+            ),
+        ));
+    }
+
+    checks
+}
+
+/// Looks for IDs shared by several snippets, which `navi run` can't tell apart.
+fn check_ids() -> Check {
+    let items = match fetch_items(CONFIG.tag_rules().as_deref()) {
+        Ok((items, _, _)) => items,
+        Err(e) => return check("snippet IDs", Status::Fail, format!("{e:#}")),
+    };
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for item in &items {
+        *counts.entry(item.id.as_str()).or_default() += 1;
+    }
+    let mut shared: Vec<&str> = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(id, _)| id)
+        .collect();
+    shared.sort_unstable();
+
+    if shared.is_empty() {
+        check(
+            "snippet IDs",
+            Status::Pass,
+            format!("{} snippets with distinct IDs", items.len()),
+        )
+    } else {
+        check(
+            "snippet IDs",
+            Status::Warn,
+            format!(
+                "{} used by more than one snippet, add `; id:` metacomments",
+                shared.join(", ")
+            ),
+        )
+    }
+}
+
+fn check_log_file() -> Check {
+    match filesystem::log_pathbuf() {
+        Ok(file) if file.parent().is_some_and(Path::exists) => check(
+            "log",
+            Status::Pass,
+            format!("logging to {}", file.display()),
+        ),
+        Ok(file) => check(
+            "log",
+            Status::Pass,
+            format!(
+                "not logging, as the directory of {} doesn't exist",
+                file.display()
+            ),
+        ),
+        Err(e) => check("log", Status::Warn, format!("{e:#}")),
+    }
+}
+
+/// Looks for the widget in the startup files of the shell the user is running.
+fn check_widget() -> Check {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let shell = Path::new(&shell)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let Ok(home) = etcetera::home_dir() else {
+        return check("widget", Status::Warn, "the home directory can't be found");
+    };
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config"));
+    let zdotdir = std::env::var_os("ZDOTDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.clone());
+
+    let (files, install) = match shell.as_str() {
+        "bash" => (
+            vec![home.join(".bashrc"), home.join(".bash_profile")],
+            r#"eval "$(navi widget bash)""#,
+        ),
+        "zsh" => (vec![zdotdir.join(".zshrc")], r#"eval "$(navi widget zsh)""#),
+        "fish" => (
+            vec![config_dir.join("fish").join("config.fish")],
+            "navi widget fish | source",
+        ),
+        "elvish" => (
+            vec![
+                config_dir.join("elvish").join("rc.elv"),
+                home.join(".elvish").join("rc.elv"),
+            ],
+            "eval (navi widget elvish | slurp)",
+        ),
+        "nu" => (
+            vec![config_dir.join("nushell").join("config.nu")],
+            r#"source ($nu.default-config-dir | path join "navi-integration.nu")"#,
+        ),
+        "" => return check("widget", Status::Warn, "the current shell is unknown"),
+        shell => {
+            return check(
+                "widget",
+                Status::Warn,
+                format!("can't check the widget of `{shell}`"),
+            );
+        }
+    };
+
+    let installed_in = files.iter().find(|file| {
+        std::fs::read_to_string(file).is_ok_and(|content| {
+            ["navi widget", "navi.plugin", "navi-integration"]
+                .iter()
+                .any(|marker| content.contains(marker))
+        })
+    });
+
+    match installed_in {
+        Some(file) => check(
+            "widget",
+            Status::Pass,
+            format!("installed in {}", file.display()),
+        ),
+        None => check(
+            "widget",
+            Status::Warn,
+            format!("not installed, add `{install}` to {}", files[0].display()),
+        ),
+    }
+}
+
+fn print(checks: &[Check]) {
+    let colored = std::io::stdout().is_terminal();
+
+    for c in checks {
+        let status = match c.status {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        };
+        let status = if !colored {
+            status.to_string()
+        } else {
+            match c.status {
+                Status::Pass => status.green(),
+                Status::Warn => status.yellow(),
+                Status::Fail => status.red(),
+            }
+            .to_string()
+        };
+        println!("[{status}] {}: {}", c.name, c.message);
+    }
+}
+
+impl Runnable for Input {
+    fn run(&self) -> Result<()> {
+        let mut checks = vec![check_config(), check_fzf()];
+        checks.push(check_shell("shell", "shell.command", &CONFIG.shell()));
+        checks.push(check_shell(
+            "finder shell",
+            "shell.finder_command",
+            &CONFIG.finder_shell(),
+        ));
+        checks.push(check_clipboard());
+        checks.extend(check_cheats());
+        checks.push(check_ids());
+        checks.push(check_log_file());
+        checks.push(check_widget());
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&checks)?);
+        } else {
+            print(&checks);
+        }
+
+        if checks.iter().any(|c| c.status == Status::Fail) {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+}
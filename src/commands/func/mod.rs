@@ -41,7 +41,7 @@ impl Runnable for Input {
             Func::Welcome => core::main(),
             Func::WidgetLastCommand => widget::last_command(),
             Func::MapExpand => map::expand(),
-            Func::Temp => temp::main(),
+            Func::Temp => temp::main(args),
         }
     }
 }
@@ -0,0 +1,114 @@
+use crate::commands::core::{actor, fetch_items};
+use crate::common::types::VariableCache;
+use crate::display::terminal::DELIMITER;
+use crate::finder::structures::{Opts as FinderOpts, SuggestionType};
+use crate::history::{self, Action, Entry};
+use crate::prelude::*;
+use crate::structures::item::Item;
+use clap::Args;
+use crossterm::style::{Stylize, style};
+
+#[derive(Debug, Clone, Args)]
+pub struct Input {}
+
+fn status(entry: &Entry) -> String {
+    match (entry.action, entry.exit_status) {
+        (Action::Executed, Some(0)) => "ok".to_string(),
+        (Action::Executed, Some(code)) => format!("exit {code}"),
+        (Action::Executed, None) => "killed".to_string(),
+        (Action::Copied, _) => "copied".to_string(),
+        (Action::Printed, _) => "printed".to_string(),
+    }
+}
+
+fn write_line(index: usize, entry: &Entry) -> String {
+    format!(
+        "{time} {status}{DELIMITER}{comment}{DELIMITER}{command}{DELIMITER}{index}{DELIMITER}\n",
+        time = style(&entry.time).dim(),
+        status = status(entry),
+        comment = style(&entry.comment).with(CONFIG.comment_color()),
+        command = style(entry.command.replace('\n', "  ")).with(CONFIG.snippet_color()),
+    )
+}
+
+/// The snippet of an entry, as far as the history remembers it.
+fn item(entry: &Entry) -> Item {
+    Item {
+        id: entry.id.clone(),
+        tags: entry.tags.clone(),
+        comment: entry.comment.clone(),
+        confirm: entry.confirm.clone(),
+        ..Default::default()
+    }
+}
+
+/// Runs an entry again with the same values, from the directory it first ran from.
+fn rerun(key: &str, entry: &Entry) -> Result<()> {
+    let mut run = entry.run.clone();
+    let cwd = Path::new(&entry.cwd).join(run.cwd.as_deref().unwrap_or(""));
+    run.cwd = Some(cwd.display().to_string());
+
+    let bindings: VariableCache = entry.bindings.clone().into_iter().collect();
+    let selections: VariableCache = entry.selections.clone().into_iter().collect();
+    actor::dispatch(key, &item(entry), &run, &bindings, &selections)
+}
+
+/// Prompts for the variables of an entry's snippet again, starting with the lines selected then.
+pub fn edit(entry: &Entry) -> Result<()> {
+    let (items, variables, files) = fetch_items(None)?;
+    let item = items
+        .into_iter()
+        .find(|i| i.id == entry.id)
+        .ok_or_else(|| anyhow!("The snippet `{}` can't be found anymore", entry.id))?;
+
+    actor::act(
+        Ok(("enter", item)),
+        files,
+        Some(variables),
+        entry.previous_selections(),
+    )
+}
+
+impl Runnable for Input {
+    fn run(&self) -> Result<()> {
+        let entries = history::read()?;
+        if entries.is_empty() {
+            eprintln!("The history is empty.");
+            return Ok(());
+        }
+
+        let opts = FinderOpts {
+            suggestion_type: SuggestionType::SnippetSelection,
+            overrides: CONFIG.fzf_overrides(),
+            header: Some("Enter: rerun | Ctrl+Y: copy | Ctrl+E: edit values".to_string()),
+            ..Default::default()
+        };
+
+        let (output, _) = crate::finder::call(opts, |stdin| {
+            for (index, entry) in entries.iter().enumerate().rev() {
+                stdin
+                    .write_all(write_line(index, entry).as_bytes())
+                    .context("Could not write to finder's stdin")?;
+            }
+            Ok(())
+        })
+        .context("Finder was unable to prompt with the history")?;
+
+        let mut lines = output.lines();
+        let key = lines.next().unwrap_or("enter");
+        let Some(index) = lines
+            .next()
+            .and_then(|line| line.split(DELIMITER).nth(3))
+            .and_then(|index| index.parse::<usize>().ok())
+        else {
+            return Ok(());
+        };
+        let entry = &entries[index];
+
+        match key {
+            "ctrl-e" => edit(entry),
+            "ctrl-o" => Ok(()),
+            key => rerun(key, entry),
+        }
+    }
+}
@@ -26,6 +26,50 @@ pub enum Info {
     DefaultCheatsPath,
     /// Prints the default configuration path.
     DefaultConfigPath,
+
+    /// Prints the effective configuration and the file that set each value.
+    Config,
+    /// Prints the active configuration profile.
+    Profile,
+}
+
+fn print_config() {
+    match CONFIG.profile() {
+        Some(profile) => println!("# Profile: {profile}"),
+        None => println!("# Profile: none"),
+    }
+
+    let sources = CONFIG.sources();
+    let mut policies = CONFIG.policies().peekable();
+
+    if sources.is_empty() && policies.peek().is_none() {
+        println!("# No configuration file was found: navi uses its defaults");
+        return;
+    }
+
+    println!("# Values which aren't listed use navi's defaults");
+    for (key, (value, layer)) in sources {
+        println!("{key} = {value}  # {layer}");
+    }
+
+    if policies.peek().is_some() {
+        println!();
+        println!("# Every policy is enforced");
+    }
+    for (layer, policy) in policies {
+        let Ok(toml::Value::Table(table)) = toml::Value::try_from(policy) else {
+            continue;
+        };
+        for (key, value) in table {
+            // Only show the rules the file defines
+            if value == toml::Value::Boolean(false)
+                || value.as_array().is_some_and(|rules| rules.is_empty())
+            {
+                continue;
+            }
+            println!("policy.{key} = {value}  # {layer}");
+        }
+    }
 }
 
 impl Runnable for Input {
@@ -56,6 +100,13 @@ impl Runnable for Input {
             Info::DefaultConfigPath => {
                 println!("{}", &filesystem::default_config_pathbuf()?.to_string())
             }
+
+            Info::Config => print_config(),
+            Info::Profile => {
+                if let Some(profile) = CONFIG.profile() {
+                    println!("{profile}")
+                }
+            }
         }
         Ok(())
     }
@@ -1,8 +1,13 @@
+pub mod again;
 pub mod core;
+pub mod doctor;
 pub mod func;
+pub mod history;
 pub mod info;
 pub mod preview;
 pub mod repo;
+pub mod run;
+pub mod search;
 pub mod shell;
 pub mod temp;
 
@@ -29,6 +34,16 @@ pub fn handle() -> Result<()> {
                 .run()
                 .with_context(|| format!("Failed to execute function `{:#?}`", input.func)),
 
+            Run(input) => input.run(),
+
+            Search(input) => input.run(),
+
+            Doctor(input) => input.run(),
+
+            History(input) => input.run(),
+
+            Again(input) => input.run(),
+
             Info(input) => input
                 .run()
                 .with_context(|| format!("Failed to fetch info `{:#?}`", input.info)),
@@ -1,5 +1,6 @@
 use crate::display;
 use crate::prelude::*;
+use crate::structures::item::Item;
 use clap::Args;
 use crossterm::style::{Stylize, style};
 use std::process;
@@ -13,25 +14,27 @@ pub struct Input {
     pub line: String,
 }
 
-fn extract_elements(argstr: &str) -> Result<(&str, &str, &str)> {
-    let mut parts = argstr.split(display::terminal::DELIMITER).skip(3);
-    let tags = parts.next().context("No `tags` element provided.")?;
-    let comment = parts.next().context("No `comment` element provided.")?;
-    let snippet = parts.next().context("No `snippet` element provided.")?;
-    Ok((tags, comment, snippet))
+/// Where and with which variables the snippet runs, in the form of a command line prefix.
+fn environment_prefix(item: &Item) -> String {
+    let cwd = item.cwd.iter().map(|cwd| format!("cd {cwd} && "));
+    let env = item
+        .env
+        .iter()
+        .map(|(key, value)| format!("{key}={value} "));
+    cwd.chain(env).collect()
 }
 
 impl Runnable for Input {
     fn run(&self) -> Result<()> {
-        let line = &self.line;
-
-        let (tags, comment, snippet) = extract_elements(line)?;
+        let (_, item) = display::terminal::read(&self.line, true)?;
 
         println!(
-            "{comment} {tags} \n{snippet}",
-            comment = style(comment).with(CONFIG.comment_color()),
-            tags = style(format!("[{tags}]")).with(CONFIG.tag_color()),
-            snippet = style(display::fix_newlines(snippet)).with(CONFIG.snippet_color()),
+            "{comment} {tags} {id}\n{prefix}{snippet}",
+            comment = style(&item.comment).with(CONFIG.comment_color()),
+            tags = style(format!("[{}]", item.tags)).with(CONFIG.tag_color()),
+            id = style(format!("({})", item.id)).dim(),
+            prefix = style(environment_prefix(&item)).dim(),
+            snippet = style(display::fix_newlines(&item.snippet)).with(CONFIG.snippet_color()),
         );
 
         process::exit(0)
@@ -1,6 +1,8 @@
 use crate::display;
 use crate::env_var;
 use crate::finder;
+use crate::finder::json;
+use crate::finder::structures::{self, JsonFields, Opts as FinderOpts, ValueFormat};
 use crate::prelude::*;
 use clap::Args;
 use crossterm::style::Stylize;
@@ -27,14 +29,39 @@ impl Runnable for Input {
         let snippet = env_var::must_get(env_var::PREVIEW_INITIAL_SNIPPET);
         let tags = env_var::must_get(env_var::PREVIEW_TAGS);
         let comment = env_var::must_get(env_var::PREVIEW_COMMENT);
-        let column = env_var::parse(env_var::PREVIEW_COLUMN);
-        let delimiter = env_var::get(env_var::PREVIEW_DELIMITER).ok();
-        let map = env_var::get(env_var::PREVIEW_MAP).ok();
+        // The options of the variable, as far as they affect its value
+        let opts = FinderOpts {
+            column: env_var::get(env_var::PREVIEW_COLUMN)
+                .ok()
+                .and_then(|columns| structures::parse_columns(&columns).ok()),
+            column_join: env_var::get(env_var::PREVIEW_COLUMN_JOIN).ok(),
+            delimiter: env_var::get(env_var::PREVIEW_DELIMITER).ok(),
+            map: env_var::get(env_var::PREVIEW_MAP).ok(),
+            shell: env_var::get(env_var::PREVIEW_SHELL).ok(),
+            json: env_var::get(env_var::PREVIEW_JSON_VALUE)
+                .ok()
+                .map(|path| JsonFields {
+                    display: None,
+                    value: Some(path),
+                }),
+            format: ValueFormat {
+                quote: env_var::parse(env_var::PREVIEW_QUOTE).unwrap_or_default(),
+                prefix: env_var::get(env_var::PREVIEW_PREFIX).ok(),
+                suffix: env_var::get(env_var::PREVIEW_SUFFIX).ok(),
+                join: env_var::get(env_var::PREVIEW_JOIN).ok(),
+            },
+            ..Default::default()
+        };
 
         let active_color = CONFIG.tag_color();
         let inactive_color = CONFIG.comment_color();
 
-        let mut colored_snippet = String::from(&snippet);
+        // Optional sections which aren't included or excluded yet
+        let mut colored_snippet = display::OPTIONAL_REGEX
+            .replace_all(&snippet, |caps: &regex::Captures| {
+                format!("{}{}{}", style("<[").dim(), &caps[1], style("]>").dim())
+            })
+            .into_owned();
         let mut visited_vars: HashSet<&str> = HashSet::new();
 
         let mut variables = String::from("");
@@ -105,8 +132,7 @@ impl Runnable for Input {
                 value = if env_var::get(&env_variable_name).is_ok() {
                     value
                 } else if is_current {
-                    finder::process(value, column, delimiter.as_deref(), map.clone())
-                        .expect("Unable to process value")
+                    finder::process(value, &opts).expect("Unable to process value")
                 } else {
                     "".to_string()
                 }
@@ -119,6 +145,13 @@ impl Runnable for Input {
         );
         println!("{variables}");
 
+        // The whole object of the highlighted JSON suggestion
+        if opts.json.is_some()
+            && let Some(object) = selection.lines().last().and_then(json::object)
+        {
+            println!("\n{}", serde_json::to_string_pretty(&object)?);
+        }
+
         process::exit(0)
     }
 }
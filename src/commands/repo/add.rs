@@ -1,14 +1,14 @@
 use crate::common::git;
 use crate::filesystem;
 use crate::finder;
-use crate::finder::structures::{Opts as FinderOpts, SuggestionType};
+use crate::finder::structures::{Column, Opts as FinderOpts, SuggestionType};
 use crate::prelude::*;
 use std::fs;
 use std::path;
 
 fn ask_if_should_import_all() -> Result<bool> {
     let opts = FinderOpts {
-        column: Some(1),
+        column: Some(vec![Column::Number(1)]),
         header: Some("Do you want to import all files from this repo?".to_string()),
         ..Default::default()
     };
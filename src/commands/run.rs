@@ -0,0 +1,473 @@
+use crate::commands::core::actor::{self, PreparedSnippet};
+use crate::commands::core::fetch_items;
+use crate::common::quoting;
+use crate::common::types::VariableCache;
+use crate::config::Action;
+use crate::display;
+use crate::env_var;
+use crate::history;
+use crate::prelude::*;
+use crate::structures::cheat::VariableMap;
+use crate::structures::item::Item;
+use clap::Args;
+use std::convert::Infallible;
+
+#[derive(Debug, Clone, Args)]
+pub struct Input {
+    /// Snippet ID, or a query that matches a single snippet
+    pub snippet: String,
+
+    /// Binds a value to a variable (example: --var branch=main)
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_binding)]
+    pub vars: Vec<(String, String)>,
+
+    /// Prints the snippet instead of executing it
+    #[arg(long)]
+    pub print: bool,
+
+    /// Executes the snippet, even if printing is the default
+    #[arg(long, conflicts_with = "print")]
+    #[cfg(not(feature = "disable-command-execution"))]
+    pub exec: bool,
+
+    /// Executes snippets marked with `; confirm` without asking
+    #[arg(long)]
+    pub yes: bool,
+
+    /// Includes an optional section without variables, by its content (example: --include --force)
+    #[arg(long = "include", value_name = "SECTION", allow_hyphen_values = true)]
+    pub includes: Vec<String>,
+}
+
+fn parse_binding(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{s}`"))
+}
+
+fn matches_query(item: &Item, words: &[String]) -> bool {
+    let haystack = format!("{} {} {}", item.tags, item.comment, item.snippet).to_lowercase();
+    words.iter().all(|w| haystack.contains(w))
+}
+
+/// Finds the snippet targeted by `target`, which is either an ID or a query.
+///
+/// A query resolves to the snippet whose description is exactly the query or, failing that,
+/// to the only snippet containing every word of it. Ambiguous queries are an error, and so are
+/// IDs shared by several snippets, which are listed with the `files` they were read from.
+pub fn resolve(items: &[Item], files: &[String], target: &str) -> Result<Item> {
+    let target = target.trim();
+
+    let mut candidates: Vec<&Item> = items.iter().filter(|i| i.id == target).collect();
+
+    if candidates.len() > 1 {
+        return Err(anyhow!(
+            "The ID `{}` is used by {} snippets, please give them distinct `; id:` metacomments:\n{}",
+            target,
+            candidates.len(),
+            candidates
+                .iter()
+                .map(|i| {
+                    let file = i
+                        .file_index
+                        .and_then(|index| files.get(index))
+                        .map_or("?", String::as_str);
+                    format!("  {file}:{}  {}", i.line, i.comment)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+
+    if candidates.is_empty()
+        && let Ok(hash) = target.parse::<u64>()
+    {
+        candidates = items.iter().filter(|i| i.hash() == hash).collect();
+    }
+
+    if candidates.is_empty() {
+        candidates = items
+            .iter()
+            .filter(|i| i.comment.eq_ignore_ascii_case(target))
+            .collect();
+    }
+
+    if candidates.is_empty() {
+        let words: Vec<String> = target.split_whitespace().map(str::to_lowercase).collect();
+        candidates = items.iter().filter(|i| matches_query(i, &words)).collect();
+    }
+
+    match candidates.as_slice() {
+        [] => Err(anyhow!("No snippet matches `{target}`")),
+        [item] => Ok((*item).clone()),
+        _ => Err(anyhow!(
+            "`{}` matches {} snippets, please use a unique ID or a more specific query:\n{}",
+            target,
+            candidates.len(),
+            candidates
+                .iter()
+                .map(|i| format!("  {}  {}", i.id, i.comment))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )),
+    }
+}
+
+/// The value of a variable from, in order of precedence, the `--var` flags, the environment
+/// and the `--query` of its suggestion.
+fn lookup(
+    item: &Item,
+    variables: &VariableMap,
+    flags: &[(String, String)],
+    variable_name: &str,
+) -> Option<String> {
+    flags
+        .iter()
+        .rev()
+        .find(|(name, _)| name == variable_name)
+        .map(|(_, value)| value.clone())
+        .or_else(|| env_var::get(env_var::escape(variable_name)).ok())
+        .or_else(|| {
+            let (_, opts) = variables.get_suggestion(&item.tags, variable_name)?;
+            opts.as_ref()?.query.clone()
+        })
+}
+
+/// Includes the optional sections given with `--include`, as well as those whose variables are
+/// all bound, and removes the others.
+fn resolve_optional_sections(
+    item: &Item,
+    variables: &VariableMap,
+    flags: &[(String, String)],
+    includes: &[String],
+) -> Result<Item> {
+    let mut included = HashSet::new();
+    let mut resolve = |text: &str| {
+        display::resolve_optional_sections(text, |section| {
+            if let Some(include) = includes.iter().find(|i| i.trim() == section.trim()) {
+                included.insert(include.as_str());
+                return Ok(true);
+            }
+            let mut names = display::VAR_REGEX
+                .captures_iter(section)
+                .map(|caps| caps[1].to_string())
+                .peekable();
+            Ok(names.peek().is_some()
+                && names.all(|name| lookup(item, variables, flags, &name).is_some()))
+        })
+    };
+
+    let mut resolved = item.clone();
+    resolved.snippet = resolve(&item.snippet)?;
+    resolved.cwd = item.cwd.as_deref().map(&mut resolve).transpose()?;
+    resolved.shell = item.shell.as_deref().map(&mut resolve).transpose()?;
+    resolved.env = item
+        .env
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), resolve(value)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(include) = includes.iter().find(|i| !included.contains(i.as_str())) {
+        return Err(anyhow!(
+            "The snippet has no optional section `{include}` to include"
+        ));
+    }
+
+    Ok(resolved)
+}
+
+/// Binds every variable of the snippet, its working directory, environment and interpreter from, in order of precedence,
+/// the `--var` flags, the environment and the `--query` of its suggestion.
+fn bind_variables(
+    item: &Item,
+    variables: &VariableMap,
+    flags: &[(String, String)],
+) -> Result<VariableCache> {
+    let mut bindings = VariableCache::new();
+    let mut missing: Vec<&str> = vec![];
+
+    let texts = item
+        .cwd
+        .iter()
+        .chain(item.env.iter().map(|(_, value)| value))
+        .chain(item.shell.iter())
+        .chain([&item.snippet]);
+
+    for variable_ref in texts
+        .flat_map(|text| display::VAR_REGEX.find_iter(text))
+        .map(|m| m.as_str())
+    {
+        let variable_name = &variable_ref[1..variable_ref.len() - 1];
+        if bindings.contains_key(variable_name) || missing.contains(&variable_name) {
+            continue;
+        }
+
+        match lookup(item, variables, flags, variable_name) {
+            Some(v) => {
+                bindings.insert(variable_name.to_string(), v);
+            }
+            None => missing.push(variable_name),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Missing values for variables: {}. Bind them with `--var <name>=<value>`",
+            missing.join(", ")
+        ));
+    }
+
+    Ok(bindings)
+}
+
+/// Replaces the bound variables of `text` in a single pass, so that values containing `<other>`
+/// are written as is. `write` gives the text written for a variable, its value and the text
+/// before it.
+fn replace_bound<E>(
+    text: &str,
+    bindings: &VariableCache,
+    mut write: impl FnMut(&str, &str, &str) -> Result<String, E>,
+) -> Result<String, E> {
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for caps in display::VAR_REGEX.captures_iter(text) {
+        let variable_ref = caps.get(0).expect("a match has a group 0");
+        let Some(value) = bindings.get(&caps[1]) else {
+            continue;
+        };
+        if variable_ref.as_str().starts_with('\\') {
+            continue;
+        }
+        replaced.push_str(&text[last..variable_ref.start()]);
+        replaced.push_str(&write(&caps[1], value, &text[..variable_ref.start()])?);
+        last = variable_ref.end();
+    }
+    replaced.push_str(&text[last..]);
+
+    Ok(replaced)
+}
+
+fn interpolate(text: &str, bindings: &VariableCache) -> String {
+    let Ok(interpolated) = replace_bound(text, bindings, |_, value, _| {
+        Ok::<_, Infallible>(value.to_string())
+    });
+    interpolated
+}
+
+/// Like [`interpolate`], escaping the values where they're written with `shell.quote_values`.
+fn interpolate_snippet(
+    item: &Item,
+    variables: &VariableMap,
+    bindings: &VariableCache,
+) -> Result<String> {
+    let interpreter = item.shell.as_deref().map(|s| interpolate(s, bindings));
+    let Some(syntax) = quoting::snippet_syntax(interpreter.as_deref()) else {
+        return Ok(interpolate(&item.snippet, bindings));
+    };
+
+    replace_bound(&item.snippet, bindings, |name, value, before| {
+        if actor::is_raw(variables, &item.tags, name) {
+            return Ok(value.to_string());
+        }
+        let context = quoting::context_after(before, syntax);
+        quoting::escape(value, syntax, context)
+            .with_context(|| format!("Failed to quote the value of `{name}`"))
+    })
+}
+
+/// Fails if the snippet requires a confirmation, because of its `; confirm` metacomment or of
+/// the execution policy, unless `--yes` is given, as nothing is prompted.
+fn check_confirmation(item: &Item, prepared: &PreparedSnippet, yes: bool) -> Result<()> {
+    let command_line = prepared.command_line()?;
+
+    match actor::required_confirmation(item, [(prepared, command_line.as_str())])? {
+        Some(message) if !yes => Err(anyhow!(
+            "{message}. Use `--yes` to execute it:\n{command_line}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl Input {
+    #[cfg(feature = "disable-command-execution")]
+    fn action(&self) -> Action {
+        Action::Print
+    }
+
+    #[cfg(not(feature = "disable-command-execution"))]
+    fn action(&self) -> Action {
+        if self.print || CONFIG.read_only() {
+            Action::Print
+        } else if self.exec {
+            Action::Execute
+        } else {
+            CONFIG.action()
+        }
+    }
+}
+
+impl Runnable for Input {
+    fn run(&self) -> Result<()> {
+        let (items, variables, files) = fetch_items(CONFIG.tag_rules().as_deref())?;
+        let item = resolve(&items, &files, &self.snippet)?;
+        debug!(item = ?item);
+        // The item itself is kept for the history, which is keyed by the original snippet
+        let resolved = resolve_optional_sections(&item, &variables, &self.vars, &self.includes)?;
+
+        let bindings = bind_variables(&resolved, &variables, &self.vars)
+            .with_context(|| format!("Unable to run `{}`", item.comment))?;
+
+        let interpolated_snippet = {
+            let mut s = interpolate_snippet(&resolved, &variables, &bindings)?;
+            s = actor::with_absolute_path(s);
+            s = display::with_new_lines(s);
+            s
+        };
+        let prepared = PreparedSnippet::new(
+            interpolated_snippet,
+            resolved.shell.as_deref().map(|s| interpolate(s, &bindings)),
+            resolved
+                .cwd
+                .as_deref()
+                .map(|cwd| interpolate(cwd, &bindings)),
+            resolved
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), interpolate(value, &bindings)))
+                .collect(),
+        );
+        let command_line = prepared.command_line()?;
+
+        match self.action() {
+            Action::Print => {
+                println!("{command_line}");
+                actor::record(
+                    &item,
+                    history::Action::Printed,
+                    &prepared,
+                    &bindings,
+                    &VariableCache::new(),
+                );
+            }
+            Action::Execute => {
+                check_confirmation(&item, &prepared, self.yes)?;
+                actor::execute_and_record(&item, &prepared, &bindings, &VariableCache::new())?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(comment: &str, snippet: &str) -> Item {
+        Item {
+            id: comment.to_lowercase().replace(' ', "-"),
+            tags: "git".to_string(),
+            comment: comment.to_string(),
+            snippet: snippet.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let items = [
+            item("Change branch", "git checkout <branch>"),
+            item("Delete branch", "git branch -D <branch>"),
+            item("Show status", "git status"),
+        ];
+
+        let by_id = resolve(&items, &[], "show-status").unwrap();
+        assert_eq!(by_id.comment, "Show status");
+
+        let by_hash = resolve(&items, &[], &items[2].hash().to_string()).unwrap();
+        assert_eq!(by_hash.comment, "Show status");
+
+        let by_comment = resolve(&items, &[], "change branch").unwrap();
+        assert_eq!(by_comment.comment, "Change branch");
+
+        let by_words = resolve(&items, &[], "branch -D").unwrap();
+        assert_eq!(by_words.comment, "Delete branch");
+
+        assert!(resolve(&items, &[], "branch").is_err());
+        assert!(resolve(&items, &[], "rebase").is_err());
+        let mut duplicate = item("Switch branch", "git switch <branch>");
+        duplicate.id = "show-status".to_string();
+        duplicate.file_index = Some(0);
+        duplicate.line = 12;
+        let items = [items[2].clone(), duplicate];
+        let err = resolve(&items, &["git.cheat".to_string()], "show-status").unwrap_err();
+        assert!(err.to_string().contains("git.cheat:12  Switch branch"));
+    }
+
+    #[test]
+    fn test_resolve_optional_sections() {
+        let item = item(
+            "Push",
+            "git push<[ --force]><[ --repo <navi_test_repo>]> <branch>",
+        );
+        let flags = [("navi_test_repo".to_string(), "origin".to_string())];
+
+        let variables = VariableMap::default();
+
+        let resolved = resolve_optional_sections(&item, &variables, &flags, &[]).unwrap();
+        assert_eq!(
+            resolved.snippet,
+            "git push --repo <navi_test_repo> <branch>"
+        );
+
+        let resolved = resolve_optional_sections(&item, &variables, &[], &[]).unwrap();
+        assert_eq!(resolved.snippet, "git push <branch>");
+
+        // Sections without variables are only included on request
+        let includes = ["--force".to_string()];
+        let resolved = resolve_optional_sections(&item, &variables, &[], &includes).unwrap();
+        assert_eq!(resolved.snippet, "git push --force <branch>");
+
+        let includes = ["--forse".to_string()];
+        let err = resolve_optional_sections(&item, &variables, &[], &includes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The snippet has no optional section `--forse` to include"
+        );
+    }
+
+    #[test]
+    fn test_check_confirmation() {
+        let mut item = item("Delete pods", "kubectl delete pods --all");
+        let prepared = PreparedSnippet::new(item.snippet.clone(), None, None, vec![]);
+        assert!(check_confirmation(&item, &prepared, false).is_ok());
+
+        item.confirm = Some("This deletes every pod".to_string());
+        let err = check_confirmation(&item, &prepared, false).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("This deletes every pod. Use `--yes`")
+        );
+        assert!(check_confirmation(&item, &prepared, true).is_ok());
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let bindings = VariableCache::from([
+            ("a".to_string(), "<b>".to_string()),
+            ("b".to_string(), "x".to_string()),
+        ]);
+        // Values are never replaced again, whatever the order of the bindings
+        assert_eq!(interpolate("<a> <b> <c>", &bindings), "<b> x <c>");
+    }
+
+    #[test]
+    fn test_parse_binding() {
+        assert_eq!(
+            parse_binding("query=a=b"),
+            Ok(("query".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_binding("query").is_err());
+    }
+}
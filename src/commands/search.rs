@@ -0,0 +1,190 @@
+use crate::commands::core::fetch_items;
+use crate::display;
+use crate::prelude::*;
+use crate::structures::item::Item;
+use clap::{Args, ValueEnum};
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum Format {
+    Tsv,
+    Json,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct Input {
+    /// Words that all must be present in a snippet's tags, description or command
+    #[arg(default_value = "")]
+    pub query: String,
+
+    /// Filter for tags, with the same syntax as navi's --tag-rules
+    #[arg(long)]
+    pub tag_rules: Option<String>,
+
+    /// Output format
+    #[arg(long, ignore_case = true, default_value = "tsv")]
+    pub format: Format,
+
+    /// Maximum number of matches
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct Match<'a> {
+    id: &'a str,
+    tags: Vec<&'a str>,
+    comment: &'a str,
+    snippet: String,
+    file: Option<&'a str>,
+    line: usize,
+    variables: Vec<&'a str>,
+}
+
+/// Scores how well an item matches the query words, or `None` if a word is missing.
+///
+/// Words found in the description weigh more than in tags, which weigh more than in the command.
+fn score(item: &Item, words: &[String]) -> Option<usize> {
+    let comment = item.comment.to_lowercase();
+    let tags = item.tags.to_lowercase();
+    let snippet = item.snippet.to_lowercase();
+
+    let mut total = 0;
+    for word in words {
+        let s = 3 * usize::from(comment.contains(word))
+            + 2 * usize::from(tags.contains(word))
+            + usize::from(snippet.contains(word));
+        if s == 0 {
+            return None;
+        }
+        total += s;
+    }
+
+    if !words.is_empty() && comment == words.join(" ") {
+        total += 10;
+    }
+
+    Some(total)
+}
+
+/// Returns the items matching every word of the query, best matches first.
+/// Ties keep the order in which snippets were read.
+pub fn rank<'a>(items: &'a [Item], query: &str) -> Vec<&'a Item> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+
+    let mut scored: Vec<(usize, &Item)> = items
+        .iter()
+        .filter_map(|item| score(item, &words).map(|s| (s, item)))
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+fn variable_names(snippet: &str) -> Vec<&str> {
+    let mut names = vec![];
+    for m in display::VAR_REGEX.captures_iter(snippet) {
+        if let Some(name) = m.get(1).map(|n| n.as_str())
+            && !names.contains(&name)
+        {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+impl Runnable for Input {
+    fn run(&self) -> Result<()> {
+        let tag_rules = self.tag_rules.clone().or_else(|| CONFIG.tag_rules());
+        let (items, _, files) = fetch_items(tag_rules.as_deref())?;
+
+        let matches: Vec<Match> = rank(&items, &self.query)
+            .into_iter()
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|item| Match {
+                id: &item.id,
+                tags: item.tags.split(',').map(str::trim).collect(),
+                comment: &item.comment,
+                snippet: display::with_new_lines(item.snippet.clone()),
+                file: item
+                    .file_index
+                    .and_then(|i| files.get(i))
+                    .map(String::as_str),
+                line: item.line,
+                variables: variable_names(&item.snippet),
+            })
+            .collect();
+
+        match self.format {
+            Format::Json => {
+                println!("{}", serde_json::to_string_pretty(&matches)?);
+            }
+            Format::Tsv => {
+                for m in matches {
+                    let fields = [
+                        m.id.to_string(),
+                        m.tags.join(","),
+                        m.comment.to_string(),
+                        m.snippet,
+                        m.file.unwrap_or("").to_string(),
+                        m.line.to_string(),
+                        m.variables.join(","),
+                    ];
+                    let fields: Vec<String> = fields.iter().map(|f| escape_tsv(f)).collect();
+                    println!("{}", fields.join("\t"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(tags: &str, comment: &str, snippet: &str) -> Item {
+        Item {
+            tags: tags.to_string(),
+            comment: comment.to_string(),
+            snippet: snippet.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let items = [
+            item("docker", "List containers", "docker ps -a"),
+            item("git", "Show git log", "git log --oneline"),
+            item("git", "Change branch", "git checkout <branch>"),
+        ];
+
+        let ranked: Vec<_> = rank(&items, "git").iter().map(|i| &i.comment).collect();
+        assert_eq!(ranked, ["Show git log", "Change branch"]);
+
+        let ranked: Vec<_> = rank(&items, "change branch")
+            .iter()
+            .map(|i| &i.comment)
+            .collect();
+        assert_eq!(ranked, ["Change branch"]);
+
+        assert_eq!(rank(&items, "").len(), 3);
+        assert!(rank(&items, "kubectl").is_empty());
+    }
+
+    #[test]
+    fn test_variable_names() {
+        assert_eq!(
+            variable_names("ssh -i <key> <user>@<host> -l <user>"),
+            ["key", "user", "host"]
+        );
+    }
+}
@@ -5,16 +5,19 @@ use crate::parser::Parser;
 use crate::{display, prelude::*};
 use std::io::{self, Write};
 
-pub fn main() -> Result<()> {
+pub fn main(args: Vec<String>) -> Result<()> {
     let _config = &CONFIG;
     let _opts = FinderOpts::snippet_default();
 
     let fetcher = get_fetcher()?;
-    let hash: u64 = 2087294461664323320;
+    let id = args
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No snippet ID specified"))?;
 
     let mut buf = vec![];
     let mut parser = Parser::new(&mut buf, false);
-    parser.set_hash(hash);
+    parser.set_id(&id);
 
     let _res = fetcher
         .fetch(&mut parser)
@@ -1,6 +1,9 @@
 use crate::common::shell::{self, EOF, ShellSpawnError};
 use crate::prelude::*;
 
+/// The tools [`copy`] looks for, in this order.
+pub const TOOLS: [&str; 3] = ["pbcopy", "xclip", "clip.exe"];
+
 pub fn copy(text: String) -> Result<()> {
     let cmd = r#"
 exst() {
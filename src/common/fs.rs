@@ -48,6 +48,61 @@ pub fn read_lines(filename: &Path) -> Result<impl Iterator<Item = Result<String>
         .map(|line| line.map_err(Error::from)))
 }
 
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Looks for an executable in the directories of `PATH`, like `which` does.
+pub fn which(binary: &str) -> Option<PathBuf> {
+    which_in(binary, &std::env::var_os("PATH")?)
+}
+
+/// Looks for an executable in the directories of a `PATH`-like list.
+fn which_in(binary: &str, path_var: &OsStr) -> Option<PathBuf> {
+    let candidates = |dir: PathBuf| {
+        let mut paths = vec![dir.join(binary)];
+        if cfg!(target_family = "windows") {
+            let exts = std::env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT".to_string());
+            paths.extend(
+                exts.split(';')
+                    .map(|ext| dir.join(format!("{binary}{ext}"))),
+            );
+        }
+        paths
+    };
+
+    if binary.contains(std::path::MAIN_SEPARATOR) {
+        let path = PathBuf::from(binary);
+        return is_executable(&path).then_some(path);
+    }
+
+    std::env::split_paths(path_var)
+        .flat_map(candidates)
+        .find(|p| is_executable(p))
+}
+
+/// Replaces a leading `~` with the home directory.
+pub fn expand_home(path: &str) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return path.to_string(),
+    };
+
+    match etcetera::home_dir() {
+        Ok(home) => format!("{}{rest}", home.display()),
+        Err(_) => path.to_string(),
+    }
+}
+
 pub fn pathbuf_to_string(pathbuf: &Path) -> Result<String> {
     Ok(pathbuf
         .as_os_str()
@@ -112,3 +167,33 @@ pub fn remove_dir(path: &Path) -> Result<()> {
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_which_in() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("navi-which-{}", std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+        create_dir_all(&first).unwrap();
+        create_dir_all(&second).unwrap();
+        for (dir, mode) in [(&first, 0o644), (&second, 0o755)] {
+            let tool = dir.join("tool");
+            fs::write(&tool, "").unwrap();
+            fs::set_permissions(&tool, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let path_var = std::env::join_paths([&root.join("missing"), &first, &second]).unwrap();
+
+        // The file of the first directory isn't executable
+        assert_eq!(which_in("tool", &path_var), Some(second.join("tool")));
+        assert_eq!(which_in("other", &path_var), None);
+        let absolute = second.join("tool").to_string_lossy().to_string();
+        assert_eq!(which_in(&absolute, &path_var), Some(second.join("tool")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -3,6 +3,7 @@ pub mod deps;
 pub mod fs;
 pub mod git;
 pub mod hash;
+pub mod quoting;
 pub mod shell;
 pub mod terminal;
 pub mod types;
@@ -0,0 +1,198 @@
+//! Quoting of the values written into a command line, depending on where they're written.
+
+use super::shell;
+use crate::prelude::*;
+
+/// The quoting rules of a shell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    /// bash, zsh, dash, ...
+    Posix,
+    Fish,
+    Nu,
+    PowerShell,
+    Cmd,
+}
+
+impl Syntax {
+    /// The syntax of the shell `command` starts, if it's a shell navi knows.
+    pub fn of(command: &str) -> Option<Self> {
+        let program = command.split_whitespace().next().unwrap_or_default();
+        let name = Path::new(program)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "ash" => Some(Self::Posix),
+            "fish" => Some(Self::Fish),
+            "nu" => Some(Self::Nu),
+            "pwsh" | "powershell" => Some(Self::PowerShell),
+            "cmd" => Some(Self::Cmd),
+            _ => None,
+        }
+    }
+
+    /// The character escaping the next one, outside of single quotes.
+    fn escape_char(self) -> Option<char> {
+        match self {
+            Self::Posix | Self::Fish | Self::Nu => Some('\\'),
+            Self::PowerShell => Some('`'),
+            Self::Cmd => None,
+        }
+    }
+}
+
+/// Where a value is written in a command line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Context {
+    Unquoted,
+    SingleQuoted,
+    DoubleQuoted,
+}
+
+/// The context at the end of `prefix`, the part of a command line before a value.
+pub fn context_after(prefix: &str, syntax: Syntax) -> Context {
+    let mut context = Context::Unquoted;
+    let mut chars = prefix.chars();
+
+    while let Some(c) = chars.next() {
+        context = match (context, c) {
+            (Context::SingleQuoted, '\'') => Context::Unquoted,
+            // Only fish has escapes in single quotes
+            (Context::SingleQuoted, '\\') if syntax == Syntax::Fish => {
+                chars.next();
+                context
+            }
+            (Context::SingleQuoted, _) => context,
+            (Context::DoubleQuoted, '"') => Context::Unquoted,
+            (Context::Unquoted, '\'') if syntax != Syntax::Cmd => Context::SingleQuoted,
+            (Context::Unquoted, '"') => Context::DoubleQuoted,
+            (_, c) if Some(c) == syntax.escape_char() => {
+                chars.next();
+                context
+            }
+            (Context::Unquoted, '^') if syntax == Syntax::Cmd => {
+                chars.next();
+                context
+            }
+            _ => context,
+        };
+    }
+
+    context
+}
+
+/// Escapes a value, so that it's a single, literal word where it's written.
+pub fn escape(value: &str, syntax: Syntax, context: Context) -> Result<String> {
+    let escape_chars = |specials: &[char], escape: char| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if specials.contains(&c) {
+                escaped.push(escape);
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+
+    Ok(match (syntax, context) {
+        (_, Context::Unquoted) => shell::quote_word(syntax, value),
+        (Syntax::Posix, Context::SingleQuoted) => value.replace('\'', r"'\''"),
+        (Syntax::Posix, Context::DoubleQuoted) => escape_chars(&['"', '\\', '$', '`'], '\\'),
+        (Syntax::Fish, Context::SingleQuoted) => escape_chars(&['\'', '\\'], '\\'),
+        (Syntax::Fish, Context::DoubleQuoted) => escape_chars(&['"', '\\', '$'], '\\'),
+        (Syntax::Nu, Context::SingleQuoted) if value.contains('\'') => {
+            return Err(anyhow!(
+                "The value `{value}` can't be written between single quotes with nushell"
+            ));
+        }
+        (Syntax::Nu, Context::SingleQuoted) => value.to_string(),
+        (Syntax::Nu, Context::DoubleQuoted) => escape_chars(&['"', '\\'], '\\'),
+        (Syntax::PowerShell, Context::SingleQuoted) => value.replace('\'', "''"),
+        (Syntax::PowerShell, Context::DoubleQuoted) => escape_chars(&['"', '`', '$'], '`'),
+        (Syntax::Cmd, _) => value.replace('"', "\"\""),
+    })
+}
+
+/// Replaces every `variable_ref` of a command line with a value, escaped where it's written.
+pub fn replace(text: &str, variable_ref: &str, value: &str, syntax: Syntax) -> Result<String> {
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for (start, _) in text.match_indices(variable_ref) {
+        replaced.push_str(&text[last..start]);
+        replaced.push_str(&escape(
+            value,
+            syntax,
+            context_after(&text[..start], syntax),
+        )?);
+        last = start + variable_ref.len();
+    }
+    replaced.push_str(&text[last..]);
+
+    Ok(replaced)
+}
+
+/// The syntax values are quoted with in a snippet run by `interpreter`, or else the configured shell.
+///
+/// Values are written as is unless `shell.quote_values` is set, as well as in snippets run by
+/// programs which aren't shells, such as `python3`.
+pub fn snippet_syntax(interpreter: Option<&str>) -> Option<Syntax> {
+    if !CONFIG.quote_values() {
+        return None;
+    }
+    match interpreter {
+        Some(interpreter) => Syntax::of(interpreter),
+        None => Syntax::of(&CONFIG.shell()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_after() {
+        let posix = |prefix| context_after(prefix, Syntax::Posix);
+        assert_eq!(posix("echo "), Context::Unquoted);
+        assert_eq!(posix("echo 'a"), Context::SingleQuoted);
+        assert_eq!(posix(r#"echo "it's "#), Context::DoubleQuoted);
+        assert_eq!(posix(r#"echo \" "#), Context::Unquoted);
+        assert_eq!(posix(r#"echo "a\" "#), Context::DoubleQuoted);
+        assert_eq!(posix(r"echo 'a\' "), Context::Unquoted);
+        assert_eq!(
+            context_after(r"echo 'a\' ", Syntax::Fish),
+            Context::SingleQuoted
+        );
+        assert_eq!(
+            context_after("echo 'it''s ", Syntax::PowerShell),
+            Context::SingleQuoted
+        );
+        assert_eq!(context_after("echo 'a ", Syntax::Cmd), Context::Unquoted);
+    }
+
+    #[test]
+    fn test_replace() {
+        let value = r#"a b; rm "$x" 'y'"#;
+        let snippet = r#"echo <v> "<v>" '<v>'"#;
+
+        assert_eq!(
+            replace(snippet, "<v>", value, Syntax::Posix).unwrap(),
+            r#"echo 'a b; rm "$x" '\''y'\''' "a b; rm \"\$x\" 'y'" 'a b; rm "$x" '\''y'\'''"#
+        );
+        assert_eq!(
+            replace(snippet, "<v>", value, Syntax::Fish).unwrap(),
+            r#"echo 'a b; rm "$x" \'y\'' "a b; rm \"\$x\" 'y'" 'a b; rm "$x" \'y\''"#
+        );
+        assert_eq!(
+            replace(snippet, "<v>", value, Syntax::PowerShell).unwrap(),
+            r#"echo 'a b; rm "$x" ''y''' "a b; rm `"`$x`" 'y'" 'a b; rm "$x" ''y'''"#
+        );
+        assert_eq!(
+            replace("ls <v> '<v>'", "<v>", "my dir", Syntax::Nu).unwrap(),
+            "ls 'my dir' 'my dir'"
+        );
+        assert!(replace("ls '<v>'", "<v>", "it's", Syntax::Nu).is_err());
+    }
+}
@@ -1,6 +1,9 @@
+use super::quoting::Syntax;
 use crate::prelude::*;
 use clap::ValueEnum;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Child, Command};
+use std::thread;
 use thiserror::Error;
 
 pub const EOF: &str = "NAVIEOF";
@@ -52,3 +55,341 @@ pub fn out() -> Result<Command> {
     cmd.arg(dash_c);
     Ok(cmd)
 }
+
+/// How an interpreter receives the script it runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScriptInput {
+    /// As the value of a flag, like `bash -c <script>`
+    Flag(&'static str),
+    /// On stdin, like `psql < script`
+    Stdin,
+}
+
+fn script_input(program: &str) -> ScriptInput {
+    let name = Path::new(program)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+
+    match name {
+        "cmd" => ScriptInput::Flag("/c"),
+        "pwsh" | "powershell" => ScriptInput::Flag("-Command"),
+        "node" | "perl" | "ruby" => ScriptInput::Flag("-e"),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" | "nu" | "elvish" | "xonsh" => {
+            ScriptInput::Flag("-c")
+        }
+        name if name.starts_with("python") => ScriptInput::Flag("-c"),
+        _ => ScriptInput::Stdin,
+    }
+}
+
+/// Whether a word means the same to every shell without quotes.
+fn is_plain_word(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:@=,+".contains(c))
+}
+
+/// Quotes a word for POSIX shells.
+fn quote(word: &str) -> String {
+    if is_plain_word(word) {
+        return word.to_string();
+    }
+    quote_single(word)
+}
+
+/// Quotes a word with single quotes, the POSIX way.
+pub fn quote_single(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// Quotes a word with double quotes, escaping what POSIX shells would expand.
+pub fn quote_double(word: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in word.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Prefixes the characters of `word` a shell expands within double quotes with `escape`.
+fn escape_chars(word: &str, chars: &[char], escape: char) -> String {
+    let mut escaped = String::new();
+    for c in word.chars() {
+        if chars.contains(&c) {
+            escaped.push(escape);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Always quotes a word with single quotes, with the given syntax.
+pub fn single_quoted(syntax: Syntax, word: &str) -> Result<String> {
+    Ok(match syntax {
+        Syntax::Posix => quote_single(word),
+        Syntax::Fish => format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'")),
+        Syntax::Nu if !word.contains('\'') => format!("'{word}'"),
+        // Single quotes can't be escaped, but raw strings can hold them
+        Syntax::Nu => {
+            let mut hashes = "#".to_string();
+            while word.contains(&format!("'{hashes}")) {
+                hashes.push('#');
+            }
+            format!("r{hashes}'{word}'{hashes}")
+        }
+        Syntax::PowerShell => format!("'{}'", word.replace('\'', "''")),
+        Syntax::Cmd => {
+            return Err(anyhow!(
+                "cmd has no single quotes, use `--quote double` or `--quote shell` instead"
+            ));
+        }
+    })
+}
+
+/// Always quotes a word with double quotes, escaping what the shell of the given syntax would
+/// expand within them.
+pub fn double_quoted(syntax: Syntax, word: &str) -> String {
+    match syntax {
+        Syntax::Posix => quote_double(word),
+        Syntax::Fish => format!("\"{}\"", escape_chars(word, &['"', '\\', '$'], '\\')),
+        // Double quotes don't interpolate in nushell, unlike `$"..."`
+        Syntax::Nu => format!("\"{}\"", escape_chars(word, &['"', '\\'], '\\')),
+        Syntax::PowerShell => format!("\"{}\"", escape_chars(word, &['"', '$', '`'], '`')),
+        Syntax::Cmd => format!("\"{}\"", word.replace('"', "\"\"")),
+    }
+}
+
+/// Quotes a word when needed, with the syntax of the shell `shell_command` starts.
+/// Programs which aren't shells get POSIX quotes.
+pub fn quote_for_shell(shell_command: &str, word: &str) -> String {
+    quote_word(Syntax::of(shell_command).unwrap_or(Syntax::Posix), word)
+}
+
+/// Quotes a word when needed, with the given syntax.
+pub fn quote_word(syntax: Syntax, word: &str) -> String {
+    if is_plain_word(word) {
+        return word.to_string();
+    }
+
+    match syntax {
+        Syntax::Posix => quote_single(word),
+        Syntax::Fish => format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'")),
+        Syntax::Nu if !word.contains('\'') => format!("'{word}'"),
+        Syntax::Nu => format!("\"{}\"", word.replace('\\', r"\\").replace('"', "\\\"")),
+        Syntax::PowerShell => format!("'{}'", word.replace('\'', "''")),
+        Syntax::Cmd => format!("\"{}\"", word.replace('"', "\"\"")),
+    }
+}
+
+/// A program a snippet runs with instead of the configured shell,
+/// as set by `; shell: python3` or `; lang: sql via psql <dsn>`.
+#[derive(Debug)]
+pub struct Interpreter {
+    words: Vec<String>,
+    input: ScriptInput,
+}
+
+impl Interpreter {
+    pub fn new(command: &str) -> Result<Self> {
+        let words = shellwords::split(command)
+            .with_context(|| format!("Failed to parse the interpreter `{command}`"))?;
+        let program = words
+            .first()
+            .ok_or_else(|| anyhow!("Interpreter command is empty"))?;
+        let input = script_input(program);
+        Ok(Self { words, input })
+    }
+
+    /// Whether the script is written to the interpreter's stdin, which is then unavailable.
+    pub fn reads_stdin(&self) -> bool {
+        self.input == ScriptInput::Stdin
+    }
+
+    /// The command running `script`. Scripts read from stdin are written by [`spawn_script`].
+    fn command(&self, script: &str) -> Command {
+        let mut cmd = Command::new(&self.words[0]);
+        cmd.args(&self.words[1..]);
+        match self.input {
+            ScriptInput::Flag(flag) => {
+                cmd.arg(flag).arg(script);
+            }
+            ScriptInput::Stdin => {
+                cmd.stdin(Stdio::piped());
+            }
+        }
+        cmd
+    }
+
+    /// A shell command line running `script`, to be printed or copied.
+    pub fn command_line(&self, script: &str) -> String {
+        let program = self
+            .words
+            .iter()
+            .map(|w| quote(w))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        match self.input {
+            ScriptInput::Flag(flag) => format!("{program} {flag} {}", quote(script)),
+            ScriptInput::Stdin => format!("{program} <<'{EOF}'\n{script}\n{EOF}"),
+        }
+    }
+}
+
+/// Prefixes a command line with `cd <cwd> &&` and `KEY=value` assignments.
+///
+/// With `export`, the variables are exported instead, for command lines running several
+/// commands or expanding the variables themselves, which assignments don't affect.
+pub fn with_environment(
+    command_line: &str,
+    export: bool,
+    cwd: Option<&str>,
+    env: &[(String, String)],
+) -> String {
+    let mut prefix = String::new();
+
+    if let Some(cwd) = cwd {
+        prefix.push_str(&format!("cd {} && ", quote(cwd)));
+    }
+
+    for (key, value) in env {
+        if export {
+            prefix.push_str(&format!("export {key}={} && ", quote(value)));
+        } else {
+            prefix.push_str(&format!("{key}={} ", quote(value)));
+        }
+    }
+
+    format!("{prefix}{command_line}")
+}
+
+/// Spawns `script` with `interpreter`, or with the configured shell if there's none.
+///
+/// `configure` sets the command up before it's spawned, e.g. to capture its output.
+pub fn spawn_script(
+    interpreter: Option<&str>,
+    script: &str,
+    configure: impl FnOnce(&mut Command),
+) -> Result<Child> {
+    let (mut cmd, stdin) = match interpreter {
+        None => {
+            let mut cmd = out()?;
+            cmd.arg(script);
+            (cmd, None)
+        }
+        Some(interpreter) => {
+            let interpreter = Interpreter::new(interpreter)?;
+            let stdin = interpreter.reads_stdin().then(|| script.to_string());
+            (interpreter.command(script), stdin)
+        }
+    };
+
+    configure(&mut cmd);
+    debug!(cmd = ?cmd);
+
+    let mut child = cmd.spawn().map_err(|e| ShellSpawnError::new(script, e))?;
+    write_stdin(&mut child, stdin);
+
+    Ok(child)
+}
+
+/// Writes to the stdin of a child from another thread, so it can't block on a full stdout pipe.
+pub fn write_stdin(child: &mut Child, input: Option<String>) {
+    if let Some(input) = input
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_for_shell() {
+        assert_eq!(quote_for_shell("bash", "main"), "main");
+        assert_eq!(quote_for_shell("bash --norc", "it's"), r"'it'\''s'");
+        assert_eq!(
+            quote_for_shell("/usr/bin/fish", r"it's a\b"),
+            r"'it\'s a\\b'"
+        );
+        assert_eq!(quote_for_shell("nu", "a b"), "'a b'");
+        assert_eq!(quote_for_shell("nu", r#"it's "x""#), r#""it's \"x\"""#);
+        assert_eq!(quote_for_shell("pwsh.exe", "it's"), "'it''s'");
+        assert_eq!(quote_double(r#"$HOME "x""#), r#""\$HOME \"x\"""#);
+    }
+
+    #[test]
+    fn test_single_and_double_quoted() {
+        let single = |syntax| single_quoted(syntax, "it's").unwrap();
+        assert_eq!(single(Syntax::Posix), r"'it'\''s'");
+        assert_eq!(single(Syntax::Fish), r"'it\'s'");
+        assert_eq!(single(Syntax::PowerShell), "'it''s'");
+        assert_eq!(single(Syntax::Nu), "r#'it's'#");
+        assert_eq!(single_quoted(Syntax::Nu, "'#").unwrap(), "r##''#'##");
+        assert!(single_quoted(Syntax::Cmd, "a").is_err());
+
+        let word = r#"$HOME "x" \ `y`"#;
+        assert_eq!(
+            double_quoted(Syntax::Posix, word),
+            r#""\$HOME \"x\" \\ \`y\`""#
+        );
+        assert_eq!(
+            double_quoted(Syntax::Fish, word),
+            r#""\$HOME \"x\" \\ `y`""#
+        );
+        assert_eq!(double_quoted(Syntax::Nu, word), r#""$HOME \"x\" \\ `y`""#);
+        assert_eq!(
+            double_quoted(Syntax::PowerShell, word),
+            r#""`$HOME `"x`" \ ``y``""#
+        );
+        assert_eq!(double_quoted(Syntax::Cmd, word), r#""$HOME ""x"" \ `y`""#);
+    }
+
+    #[test]
+    fn test_command_line() {
+        let python = Interpreter::new("python3").unwrap();
+        assert!(!python.reads_stdin());
+        assert_eq!(
+            python.command_line("print('hi')"),
+            r#"python3 -c 'print('\''hi'\'')'"#
+        );
+
+        let pwsh = Interpreter::new("pwsh -NoProfile").unwrap();
+        assert_eq!(
+            pwsh.command_line("Get-Date"),
+            "pwsh -NoProfile -Command Get-Date"
+        );
+
+        let psql = Interpreter::new("psql 'postgres://db/app'").unwrap();
+        assert!(psql.reads_stdin());
+        assert_eq!(
+            psql.command_line("select 1;"),
+            format!("psql postgres://db/app <<'{EOF}'\nselect 1;\n{EOF}")
+        );
+    }
+
+    #[test]
+    fn test_with_environment() {
+        let env = [("AWS_PROFILE".to_string(), "my profile".to_string())];
+        assert_eq!(
+            with_environment("aws s3 ls", false, Some("/srv/app"), &env),
+            "cd /srv/app && AWS_PROFILE='my profile' aws s3 ls"
+        );
+        assert_eq!(
+            with_environment("make && make install", true, None, &env),
+            "export AWS_PROFILE='my profile' && make && make install"
+        );
+        assert_eq!(with_environment("ls", false, None, &[]), "ls");
+    }
+}
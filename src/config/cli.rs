@@ -1,6 +1,7 @@
 use crate::commands;
 
 use clap::{Parser, Subcommand, crate_authors, crate_version};
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -30,6 +31,8 @@ use clap::{Parser, Subcommand, crate_authors, crate_version};
     navi --query git                             # filter results by \"git\"
     navi --query 'create db' --best-match        # autoselect the snippet that best matches a query
     db=my navi --query 'create db' --best-match  # same, but set the value for the <name> variable
+    navi run 'create db' --var name=my --print   # print a snippet without prompting
+    navi search docker --format json             # list matching snippets as JSON
     navi repo add <user>/<repo>                  # import cheats from a git repository
     eval \"$(navi widget zsh)\"                    # load the zsh widget
     navi --fzf-overrides '--with-nth 1,2'        # show only the comment and tag columns
@@ -37,12 +40,21 @@ use clap::{Parser, Subcommand, crate_authors, crate_version};
     navi --fzf-overrides-var '--no-select-1'     # same, but for variable selection
     navi --fzf-overrides '--nth 1,2'             # only consider the first two columns for search
     navi --fzf-overrides '--no-exact'            # use looser search algorithm
-    navi --tag-rules='git,!checkout'             # show non-checkout git snippets only")]
+    navi --tag-rules='git,!checkout'             # show non-checkout git snippets only
+    navi --tag-rules='(k8s OR helm) AND NOT delete'  # combine tags with boolean operators")]
 pub(super) struct ClapConfig {
     /// Colon-separated list of paths containing .cheat files
     #[arg(short, long)]
     pub path: Option<String>,
 
+    /// Configuration file merged over the system, user and project ones [env: NAVI_CONFIG]
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Profile of the configuration to use, from its [profiles.<name>] tables [env: NAVI_PROFILE]
+    #[arg(long)]
+    pub profile: Option<String>,
+
     /// Instead of executing a snippet, prints it to stdout
     #[arg(long)]
     #[cfg(not(feature = "disable-command-execution"))]
@@ -56,7 +68,7 @@ pub(super) struct ClapConfig {
     #[arg(long)]
     pub prevent_interpolation: bool,
 
-    /// [Experimental] Comma-separated list that acts as filter for tags. Parts starting with ! represent negation
+    /// [Experimental] Filter for tags, combining them with AND, OR, NOT and parentheses. Comma-separated lists, where parts starting with ! represent negation, are also accepted
     #[arg(long)]
     pub tag_rules: Option<String>,
 
@@ -99,10 +111,20 @@ pub enum Command {
     /// Used for fzf's preview window when selecting variable suggestions
     #[command(hide = true)]
     PreviewVarStdin(commands::preview::var_stdin::Input),
+    /// Runs a snippet by ID or query, without prompting for variables
+    Run(commands::run::Input),
+    /// Lists the snippets matching a query, in a machine-readable format
+    Search(commands::search::Input),
     /// Outputs shell widget source code
     Widget(commands::shell::Input),
     /// Shows info
     Info(commands::info::Input),
+    /// Checks navi's dependencies, configuration and cheatsheets
+    Doctor(commands::doctor::Input),
+    /// Browses the snippets executed, copied or printed before, to run them again
+    History(commands::history::Input),
+    /// Selects the last snippet again, with its previous values as defaults
+    Again(commands::again::Input),
 }
 
 #[derive(Debug)]
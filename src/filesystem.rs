@@ -1,6 +1,7 @@
 pub use crate::common::fs::{create_dir, exe_string, read_lines, remove_dir};
+use crate::config::{self, LocalCheats};
 use crate::env_var;
-use crate::parser::Parser;
+use crate::parser::{self, ParsedCheat, Parser};
 use crate::prelude::*;
 
 use crate::structures::fetcher;
@@ -8,6 +9,11 @@ use etcetera::BaseStrategy;
 use regex::Regex;
 
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+use std::num::NonZeroUsize;
+use std::sync::mpsc;
+use std::thread;
 
 use walkdir::WalkDir;
 
@@ -28,6 +34,72 @@ pub fn all_cheat_files(path: &Path) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
+/// The current directory and its parents, up to the root of the git repository.
+/// The home directory is never part of them, as it isn't part of any project.
+fn project_dirs<'a>(
+    cwd: &'a Path,
+    home: Option<&'a Path>,
+    depth: usize,
+) -> impl Iterator<Item = &'a Path> {
+    let mut reached_root = false;
+
+    cwd.ancestors()
+        .take(depth + 1)
+        .take_while(move |dir| Some(*dir) != home)
+        .take_while(move |dir| {
+            let keep = !reached_root;
+            reached_root = dir.join(".git").exists();
+            keep
+        })
+}
+
+/// Cheat files kept in a project, split by whether their project is trusted.
+#[derive(Debug, Default)]
+pub struct LocalCheatFiles {
+    pub trusted: Vec<String>,
+    /// Files of projects which aren't listed in `project.trusted`, which aren't read
+    pub untrusted: Vec<String>,
+}
+
+/// Cheat files kept in a project, found in `.navi` directories (or the configured names)
+/// of the current directory and its parents.
+///
+/// Their suggestion commands run as the user's own, so only the ones of projects matching one of
+/// the `trusted` globs are read.
+pub fn local_cheat_files(
+    cwd: &Path,
+    home: Option<&Path>,
+    config: &LocalCheats,
+    trusted: &[String],
+) -> LocalCheatFiles {
+    let mut files = LocalCheatFiles::default();
+
+    for dir in project_dirs(cwd, home, config.depth) {
+        let files = if config::is_trusted_project(dir, trusted) {
+            &mut files.trusted
+        } else {
+            &mut files.untrusted
+        };
+        for name in &config.names {
+            let path = dir.join(name);
+            if path.is_dir() {
+                files.extend(all_cheat_files(&path));
+            } else if path.is_file() {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    files
+}
+
+/// The closest `.navi/config.toml` of the current project.
+pub fn project_config_pathbuf(cwd: &Path, home: Option<&Path>) -> Option<PathBuf> {
+    project_dirs(cwd, home, LocalCheats::default().depth)
+        .map(|dir| dir.join(".navi").join("config.toml"))
+        .find(|path| path.is_file())
+}
+
 fn paths_from_path_param(env_var: &str) -> impl Iterator<Item = &str> {
     env_var.split(JOIN_SEPARATOR).filter(|folder| folder != &"")
 }
@@ -41,6 +113,15 @@ pub fn default_cheat_pathbuf() -> Result<PathBuf> {
     Ok(pathbuf)
 }
 
+pub fn history_pathbuf() -> Result<PathBuf> {
+    let mut pathbuf = get_data_dir_by_platform()?;
+
+    pathbuf.push("navi");
+    pathbuf.push("history.jsonl");
+
+    Ok(pathbuf)
+}
+
 pub fn default_config_pathbuf() -> Result<PathBuf> {
     let mut pathbuf = get_config_dir_by_platform()?;
 
@@ -50,6 +131,15 @@ pub fn default_config_pathbuf() -> Result<PathBuf> {
     Ok(pathbuf)
 }
 
+/// The configuration file shared by every user of the machine.
+pub fn system_config_pathbuf() -> PathBuf {
+    if cfg!(target_family = "windows") {
+        PathBuf::from(r"C:\ProgramData\navi\config.toml")
+    } else {
+        PathBuf::from("/etc/navi/config.toml")
+    }
+}
+
 pub fn cheat_paths(path: Option<String>) -> Result<String> {
     if let Some(p) = path {
         Ok(p)
@@ -119,6 +209,43 @@ fn interpolate_paths(paths: String) -> String {
     newtext
 }
 
+/// The folders cheatsheets are read from, with environment variables and `~` expanded.
+pub fn cheat_folders(path: Option<String>) -> Result<Vec<PathBuf>> {
+    let paths = cheat_paths(path)?;
+    let interpolated_paths = interpolate_paths(paths);
+
+    use std::sync::LazyLock;
+    static HOME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^~").unwrap());
+    let home_regex = &*HOME_REGEX;
+    let home = etcetera::home_dir().ok();
+
+    Ok(paths_from_path_param(&interpolated_paths)
+        .map(|folder| match &home {
+            Some(h) => home_regex.replace(folder, h.to_string_lossy()).to_string(),
+            None => folder.to_string(),
+        })
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// The cheat files of the project navi is run from, if local cheatsheets are enabled.
+pub fn current_local_cheat_files() -> LocalCheatFiles {
+    let home = etcetera::home_dir().ok();
+    match (CONFIG.local_cheats(), std::env::current_dir()) {
+        (Some(config), Ok(cwd)) => {
+            local_cheat_files(&cwd, home.as_deref(), config, CONFIG.trusted_projects())
+        }
+        _ => LocalCheatFiles::default(),
+    }
+}
+
+/// The file navi logs to, if its directory exists.
+pub fn log_pathbuf() -> Result<PathBuf> {
+    let mut file = default_config_pathbuf()?;
+    file.set_file_name("navi.log");
+    Ok(file)
+}
+
 #[derive(Debug)]
 pub struct Fetcher {
     path: Option<String>,
@@ -134,50 +261,140 @@ impl Fetcher {
     }
 }
 
-impl fetcher::Fetcher for Fetcher {
-    fn fetch(&self, parser: &mut Parser) -> Result<bool> {
-        let mut found_something = false;
+/// A cheatsheet read by a worker thread, tagged with its discovery order and whether it's local.
+type ParsedFile = (usize, String, bool, Result<ParsedCheat>);
+
+fn parse_file(index: usize, file: &str, local: bool) -> Result<ParsedCheat> {
+    let lines = read_lines(Path::new(file))?;
+    parser::parse_lines(lines, file, Some(index)).map(|mut cheat| {
+        for item in &mut cheat.items {
+            item.local = local;
+        }
+        cheat
+    })
+}
+
+/// Parses cheat files on a pool of workers and hands them to the parser in the order they're
+/// listed, along with whether they're local. Returns whether any of them could be parsed.
+///
+/// Local files are listed first, the closest ones first, so that their snippets come first in
+/// the finder. Their variables are merged last instead, so that they take precedence.
+///
+/// `files` is iterated on its own thread, so the files can be discovered while others are parsed.
+fn consume_files(
+    files: impl Iterator<Item = (String, bool)> + Send,
+    parser: &mut Parser,
+    read_files: &mut Vec<String>,
+) -> bool {
+    let mut found_something = false;
+
+    let workers = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1);
+
+    // Results may arrive in any order, so they're buffered until every file discovered
+    // before them has been handed to the parser: this keeps `file_index`, deduplication
+    // and variable overrides the same as reading the files one after another.
+    let (file_tx, file_rx) = mpsc::channel::<(usize, String, bool)>();
+    let file_rx = Mutex::new(file_rx);
+    let (cheat_tx, cheat_rx) = mpsc::channel::<ParsedFile>();
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for (index, (file, local)) in files.enumerate() {
+                if file_tx.send((index, file, local)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        for _ in 0..workers {
+            let file_rx = &file_rx;
+            let cheat_tx = cheat_tx.clone();
+            scope.spawn(move || {
+                loop {
+                    let next = file_rx.lock().ok().and_then(|rx| rx.recv().ok());
+                    let Some((index, file, local)) = next else {
+                        break;
+                    };
+                    let cheat = parse_file(index, &file, local);
+                    if cheat_tx.send((index, file, local, cheat)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(cheat_tx);
+
+        let mut pending = BTreeMap::new();
+        let mut next_index = 0;
+        let mut local_variables = vec![];
+
+        for (index, file, local, cheat) in cheat_rx {
+            pending.insert(index, (file, local, cheat));
+
+            while let Some((file, local, cheat)) = pending.remove(&next_index) {
+                next_index += 1;
+                read_files.push(file.clone());
+
+                match cheat {
+                    Ok(mut cheat) => {
+                        if local {
+                            local_variables.push(mem::take(&mut cheat.variables));
+                        }
+                        parser.consume(cheat);
+                        found_something = true;
+                    }
+                    // A single unreadable file shouldn't hide every other cheat
+                    Err(e) => {
+                        warn!("failed to read `{file}`: {e:?}");
+                        eprintln!("Warning: skipping the cheatsheet `{file}`: {e:#}");
+                    }
+                }
+            }
+        }
 
-        let path = self.path.clone();
-        let paths = cheat_paths(path);
+        for variables in local_variables.into_iter().rev() {
+            parser.variables.merge(variables);
+        }
+    });
+
+    found_something
+}
 
-        if paths.is_err() {
+impl fetcher::Fetcher for Fetcher {
+    fn fetch(&self, parser: &mut Parser) -> Result<bool> {
+        let Ok(folders) = cheat_folders(self.path.clone()) else {
             return Ok(false);
         };
 
-        let paths = paths?;
-        let interpolated_paths = interpolate_paths(paths);
-        let folders = paths_from_path_param(&interpolated_paths);
-
-        use std::sync::LazyLock;
-        static HOME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^~").unwrap());
-        let home_regex = &*HOME_REGEX;
-        let home = etcetera::home_dir().ok();
-
-        // parser.filter = self.tag_rules.as_ref().map(|r| gen_lists(r.as_str()));
-
-        for folder in folders {
-            let interpolated_folder = match &home {
-                Some(h) => home_regex.replace(folder, h.to_string_lossy()).to_string(),
-                None => folder.to_string(),
-            };
-            let folder_pathbuf = PathBuf::from(interpolated_folder);
-            let cheat_files = all_cheat_files(&folder_pathbuf);
-            debug!("read cheat files in `{folder_pathbuf:?}`: {cheat_files:#?}");
-            for file in cheat_files {
-                self.files.borrow_mut().push(file.clone());
-                let index = self.files.borrow().len() - 1;
-                let read_file_result = {
-                    let path = PathBuf::from(&file);
-                    let lines = read_lines(&path)?;
-                    parser.read_lines(lines, &file, Some(index))
-                };
-
-                if read_file_result.is_ok() && !found_something {
-                    found_something = true
-                }
-            }
+        let LocalCheatFiles { trusted, untrusted } = current_local_cheat_files();
+        debug!("local cheat files: {trusted:#?}, untrusted: {untrusted:#?}");
+        if !untrusted.is_empty() {
+            eprintln!(
+                "Warning: skipping {} .cheat files of the current project, as it isn't listed in `project.trusted`",
+                untrusted.len()
+            );
         }
+        let local_files = trusted;
+
+        // Local cheats come first, and the files of the paths are discovered as they're parsed
+        let local_set: HashSet<_> = local_files.iter().cloned().collect();
+        let global_files = folders
+            .into_iter()
+            .flat_map(|folder_pathbuf| {
+                let cheat_files = all_cheat_files(&folder_pathbuf);
+                debug!("read cheat files in `{folder_pathbuf:?}`: {cheat_files:#?}");
+                cheat_files
+            })
+            .filter(move |file| !local_set.contains(file))
+            .map(|file| (file, false));
+        let files = local_files
+            .into_iter()
+            .map(|file| (file, true))
+            .chain(global_files);
+
+        let found_something = consume_files(files, parser, &mut self.files.borrow_mut());
 
         debug!("FilesystemFetcher = {self:#?}");
         Ok(found_something)
@@ -234,6 +451,169 @@ mod tests {
     }
     */
 
+    fn write_cheats(dir: &Path, count: usize) -> Vec<String> {
+        std::fs::create_dir_all(dir).unwrap();
+        (0..count)
+            .map(|i| {
+                let file = dir.join(format!("{i:02}.cheat"));
+                let cheat = format!("% tag{i}\n\n# first {i}\necho {i}\n\n# second {i}\necho {i}\n\n$ var{i}: echo {i}\n");
+                std::fs::write(&file, cheat).unwrap();
+                file.to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_consume_files_in_order() {
+        let root = std::env::temp_dir().join(format!("navi-fetch-{}", std::process::id()));
+        let mut files = write_cheats(&root, 30);
+        files.reverse();
+        files.insert(3, root.join("missing.cheat").to_string_lossy().to_string());
+
+        let mut output = vec![];
+        let mut parser = Parser::new(&mut output, false);
+        parser.keep_items();
+        let mut read_files = vec![];
+        let found = consume_files(
+            files.iter().map(|f| (f.clone(), false)),
+            &mut parser,
+            &mut read_files,
+        );
+        let items = parser.take_items();
+
+        // The missing file is skipped, and the others are read in the order they were listed
+        assert!(found);
+        assert_eq!(read_files, files);
+        assert_eq!(items.len(), 60);
+        for item in &items {
+            let file = Path::new(&read_files[item.file_index.unwrap()]);
+            let n: usize = file.file_stem().unwrap().to_str().unwrap().parse().unwrap();
+            assert_eq!(item.tags, format!("tag{n}"));
+        }
+        let comments: Vec<&str> = items.iter().map(|i| i.comment.as_str()).take(3).collect();
+        assert_eq!(comments, ["first 29", "second 29", "first 28"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_local_variables_take_precedence() {
+        let root = std::env::temp_dir().join(format!("navi-precedence-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let cheat = |name: &str| {
+            let file = root.join(format!("{name}.cheat"));
+            let content = format!(
+                "% git\n\n# Checkout {name}\ngit checkout <branch>\n\n$ branch: echo {name}\n"
+            );
+            std::fs::write(&file, content).unwrap();
+            file.to_string_lossy().to_string()
+        };
+        // As listed by `fetch`: the closest local file first, then the farther one, then global ones
+        let files = [
+            (cheat("closest"), true),
+            (cheat("farther"), true),
+            (cheat("global"), false),
+        ];
+
+        let mut output = vec![];
+        let mut parser = Parser::new(&mut output, false);
+        parser.keep_items();
+        consume_files(files.into_iter(), &mut parser, &mut vec![]);
+
+        let (command, _) = parser.variables.get_suggestion("git", "branch").unwrap();
+        assert_eq!(command.trim(), "echo closest");
+        let comments: Vec<String> = parser.take_items().into_iter().map(|i| i.comment).collect();
+        assert_eq!(
+            comments,
+            ["Checkout closest", "Checkout farther", "Checkout global"]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// A finder which exited before reading anything.
+    struct ClosedFinder {
+        writes: usize,
+    }
+
+    impl std::io::Write for ClosedFinder {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            self.writes += 1;
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_consume_files_after_the_finder_closed() {
+        let root = std::env::temp_dir().join(format!("navi-closed-{}", std::process::id()));
+        let files = write_cheats(&root, 3);
+
+        let mut finder = ClosedFinder { writes: 0 };
+        let mut parser = Parser::new(&mut finder, false);
+        consume_files(
+            files.iter().map(|f| (f.clone(), false)),
+            &mut parser,
+            &mut vec![],
+        );
+
+        // Nothing is written after the first failure, but every variable is still known
+        assert!(parser.variables.get_suggestion("tag2", "var2").is_some());
+        drop(parser);
+        assert_eq!(finder.writes, 1);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_local_cheat_files() {
+        let root = std::env::temp_dir().join(format!("navi-local-{}", std::process::id()));
+        let project = root.join("project");
+        let nested = project.join("src").join("nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(project.join(".git")).unwrap();
+        std::fs::create_dir_all(project.join(".navi")).unwrap();
+        std::fs::create_dir_all(root.join(".navi")).unwrap();
+        std::fs::write(project.join(".navi").join("release.cheat"), "").unwrap();
+        std::fs::write(project.join("src").join("tasks.cheat"), "").unwrap();
+        std::fs::write(root.join(".navi").join("outside.cheat"), "").unwrap();
+
+        let config = LocalCheats {
+            names: vec![".navi".to_string(), "tasks.cheat".to_string()],
+            ..Default::default()
+        };
+        let trusted = [format!("{}/*", root.display())];
+        let files = local_cheat_files(&nested, None, &config, &trusted);
+        let expected = [
+            project.join("src").join("tasks.cheat"),
+            project.join(".navi").join("release.cheat"),
+        ]
+        .map(|p| p.to_string_lossy().to_string());
+        assert_eq!(files.trusted, expected);
+        assert!(files.untrusted.is_empty());
+
+        let files = local_cheat_files(&nested, Some(&project), &config, &trusted);
+        assert_eq!(files.trusted, expected[..1]);
+
+        let shallow = LocalCheats { depth: 1, ..config };
+        let files = local_cheat_files(&nested, None, &shallow, &trusted);
+        assert_eq!(files.trusted, expected[..1]);
+
+        // The cheats of untrusted projects aren't read, so their variables can't override the
+        // ones of global cheats
+        let files = local_cheat_files(&nested, None, &shallow, &[]);
+        assert!(files.trusted.is_empty());
+        assert_eq!(files.untrusted, expected[..1]);
+        let other = [format!("{}/other", root.display())];
+        let files = local_cheat_files(&nested, None, &shallow, &other);
+        assert_eq!(files.untrusted, expected[..1]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn splitting_of_dirs_param_may_not_contain_empty_items() {
         // Trailing colon indicates potential extra path. Split returns an empty item for it. This empty item should be filtered away, which is what this test checks.
@@ -0,0 +1,135 @@
+//! Suggestions read as JSON, as set by `--json`, `--display` and `--value`.
+//!
+//! Each object becomes a finder line made of its display text and, in a hidden column,
+//! the object itself, from which the value is taken once it's selected.
+
+use super::structures::JsonFields;
+use crate::display::terminal::DELIMITER;
+use crate::prelude::*;
+use serde_json::Value;
+
+/// A field of a JSON value, such as `.metadata.name` or `.items.0`. `.` is the value itself.
+pub fn field<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => value.get(key),
+        })
+}
+
+/// A field as text: strings without quotes, and other values as compact JSON.
+pub fn field_text(value: &Value, path: &str) -> String {
+    match field(value, path) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Replaces the `{.path}` placeholders of a template with fields of a value.
+fn render(template: &str, value: &Value) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{.") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&field_text(value, &rest[start + 1..start + len]));
+        rest = &rest[start + len + 1..];
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+/// The objects of a command's output: either JSON values one after the other,
+/// such as JSON lines, or a single array of them.
+fn rows(text: &str) -> Result<Vec<Value>> {
+    let values = serde_json::Deserializer::from_str(text)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<_>, _>>()
+        .context("Suggestions of a `--json` variable aren't valid JSON")?;
+
+    Ok(match <[Value; 1]>::try_from(values) {
+        Ok([Value::Array(items)]) => items,
+        Ok([value]) => vec![value],
+        Err(values) => values,
+    })
+}
+
+/// The finder lines of a command's output.
+pub fn lines(text: &str, fields: &JsonFields) -> Result<String> {
+    let mut lines = String::new();
+
+    for row in rows(text)? {
+        let display = match (&fields.display, &fields.value) {
+            (Some(template), _) => render(template, &row),
+            (None, Some(path)) => field_text(&row, path),
+            (None, None) => row.to_string(),
+        };
+        let display = display.replace('\n', " ").replace(DELIMITER, " ");
+        lines.push_str(&format!("{display}{DELIMITER}{row}\n"));
+    }
+
+    Ok(lines)
+}
+
+/// The object of a finder line, unless the line was typed instead of selected.
+pub fn object(line: &str) -> Option<Value> {
+    let (_, json) = line.split_once(DELIMITER)?;
+    serde_json::from_str(json).ok()
+}
+
+/// The values of the selected lines. Typed lines are values as is.
+pub fn values(text: &str, fields: &JsonFields) -> String {
+    text.lines()
+        .map(|line| match object(line) {
+            Some(row) => field_text(&row, fields.value.as_deref().unwrap_or(".")),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_and_values() {
+        let fields = JsonFields {
+            display: Some("{.metadata.name} ({.status.phase})".to_string()),
+            value: Some(".metadata.name".to_string()),
+        };
+        let output = r#"[
+            {"metadata": {"name": "web"}, "status": {"phase": "Running"}},
+            {"metadata": {"name": "db"}, "status": {"phase": "Pending"}}
+        ]"#;
+
+        let lines = lines(output, &fields).unwrap();
+        let displays: Vec<&str> = lines
+            .lines()
+            .map(|l| l.split(DELIMITER).next().unwrap())
+            .collect();
+        assert_eq!(displays, ["web (Running)", "db (Pending)"]);
+
+        let selected = lines.lines().nth(1).unwrap();
+        assert_eq!(values(selected, &fields), "db");
+        assert_eq!(values("typed", &fields), "typed");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let fields = JsonFields {
+            display: None,
+            value: Some(".id".to_string()),
+        };
+        let lines = lines("{\"id\": 1}\n{\"id\": 2}\n", &fields).unwrap();
+        assert_eq!(values(&lines, &fields), "1\n2");
+
+        assert!(super::lines("not json", &fields).is_err());
+    }
+}
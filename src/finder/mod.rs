@@ -4,20 +4,23 @@ use std::io::Write;
 use std::process::{self, Output};
 use std::process::{Command, Stdio};
 pub mod structures;
-pub use post::process;
+pub use post::{process, resolve_columns};
 use structures::Opts;
 use structures::SuggestionType;
 
-const MIN_FZF_VERSION_MAJOR: u32 = 0;
-const MIN_FZF_VERSION_MINOR: u32 = 23;
-const MIN_FZF_VERSION_PATCH: u32 = 1;
+pub const MIN_FZF_VERSION_MAJOR: u32 = 0;
+pub const MIN_FZF_VERSION_MINOR: u32 = 23;
+pub const MIN_FZF_VERSION_PATCH: u32 = 1;
 
 const COLORFUL_FZF_VERSION_MAJOR: u32 = 0;
 const COLORFUL_FZF_VERSION_MINOR: u32 = 56;
 
+pub mod json;
 mod post;
+pub mod transform;
 
-fn parse(out: Output, opts: Opts) -> Result<String> {
+/// The value of the finder's output, along with the lines it selected.
+fn parse(out: Output, opts: Opts) -> Result<(String, String)> {
     let text = match out.status.code() {
         Some(0) | Some(1) | Some(2) => {
             String::from_utf8(out.stdout).context("Invalid utf8 received from finder")?
@@ -30,18 +33,24 @@ fn parse(out: Output, opts: Opts) -> Result<String> {
         }
     };
 
-    let output = post::parse_output_single(text, opts.suggestion_type)?;
-    post::process(output, opts.column, opts.delimiter.as_deref(), opts.map)
+    let selection = post::parse_output_single(text, opts.suggestion_type)?;
+    let value = post::process(selection.clone(), &opts)?;
+    Ok((value, selection))
 }
 
-fn check_fzf_version() -> Option<(u32, u32, u32)> {
+pub fn check_fzf_version() -> Option<(u32, u32, u32)> {
     let output = Command::new("fzf").arg("--version").output().ok()?.stdout;
-    let version_string = String::from_utf8(output).ok()?;
-    let version_parts: Vec<_> = version_string.split('.').collect();
+    parse_fzf_version(&String::from_utf8(output).ok()?)
+}
+
+/// Parses the output of `fzf --version`, e.g. `0.44.1 (d7d2ac3)`.
+fn parse_fzf_version(version_string: &str) -> Option<(u32, u32, u32)> {
+    let version = version_string.split_whitespace().next()?;
+    let version_parts: Vec<_> = version.split('.').collect();
     if version_parts.len() == 3 {
         let major = version_parts[0].parse().ok()?;
         let minor = version_parts[1].parse().ok()?;
-        let patch = version_parts[2].split_whitespace().next()?.parse().ok()?;
+        let patch = version_parts[2].parse().ok()?;
         Some((major, minor, patch))
     } else {
         None
@@ -49,6 +58,16 @@ fn check_fzf_version() -> Option<(u32, u32, u32)> {
 }
 
 pub fn call<F, R>(finder_opts: Opts, stdin_fn: F) -> Result<(String, R)>
+where
+    F: Fn(&mut dyn Write) -> Result<R>,
+{
+    let (output, _, return_value) = call_with_selection(finder_opts, stdin_fn)?;
+    Ok((output, return_value))
+}
+
+/// Like [`call`], along with the lines selected in the finder, before options such as
+/// `--column` or `--map` turn them into the output.
+pub fn call_with_selection<F, R>(finder_opts: Opts, stdin_fn: F) -> Result<(String, String, R)>
 where
     F: Fn(&mut dyn Write) -> Result<R>,
 {
@@ -102,6 +121,11 @@ where
         command.args(["--with-nth", "1,2,3"]);
     }
 
+    // The objects of JSON suggestions are hidden after their display text
+    if opts.json.is_some() {
+        command.args(["--with-nth", "1"]);
+    }
+
     if !opts.prevent_select1 {
         command.arg("--select-1");
     }
@@ -134,7 +158,9 @@ where
         command.args(["--filter", &f]);
     }
 
-    if let Some(d) = opts.delimiter {
+    if let Some(d) = opts.delimiter
+        && opts.json.is_none()
+    {
         command.args(["--delimiter", &d]);
     }
 
@@ -197,6 +223,21 @@ where
         .wait_with_output()
         .context("Failed to wait for finder")?;
 
-    let output = parse(out, finder_opts).context("Unable to get output")?;
-    Ok((output, return_value))
+    let (output, selection) = parse(out, finder_opts).context("Unable to get output")?;
+    Ok((output, selection, return_value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fzf_version() {
+        assert_eq!(parse_fzf_version("0.44.1 (d7d2ac3)\n"), Some((0, 44, 1)));
+        assert_eq!(parse_fzf_version("0.23.1\n"), Some((0, 23, 1)));
+        assert_eq!(parse_fzf_version("0.54.3 (brew)"), Some((0, 54, 3)));
+        assert_eq!(parse_fzf_version("0.23"), None);
+        assert_eq!(parse_fzf_version("fzf: command not found"), None);
+        assert_eq!(parse_fzf_version(""), None);
+    }
 }
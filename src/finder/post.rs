@@ -1,13 +1,51 @@
+use super::json;
+use super::transform;
+use crate::common::quoting::Syntax;
 use crate::common::shell;
-use crate::finder::structures::SuggestionType;
+use crate::finder::structures::{Column, Opts, SuggestionType};
 use crate::prelude::*;
 use shell::EOF;
 use std::process::Stdio;
 
-fn apply_map(text: String, map_fn: Option<String>) -> Result<String> {
+/// Runs a map function with an interpreter, which reads the text from stdin.
+fn apply_map_with(interpreter: &str, text: String, map_fn: &str) -> Result<String> {
+    let mut child = shell::spawn_script(Some(interpreter), map_fn, |cmd| {
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+    })?;
+    shell::write_stdin(&mut child, Some(text));
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to execute map function")?;
+
+    let text = String::from_utf8(output.stdout).context("Invalid utf8 output for map function")?;
+
+    // Like `_navi_nonewline` does for shells
+    Ok(text.trim_end_matches('\n').to_string())
+}
+
+fn apply_map(text: String, map_fn: Option<&str>, interpreter: Option<&str>) -> Result<String> {
+    if let Some(m) = map_fn
+        && transform::is_builtin(m)
+    {
+        let shell = interpreter.map_or_else(|| CONFIG.shell(), str::to_string);
+        return transform::apply(m, &text, &shell);
+    }
+
+    // Interpreters reading their script from stdin can't also read the text there,
+    // so their map functions run with the configured shell
+    if let (Some(m), Some(interpreter)) = (map_fn, interpreter)
+        && !shell::Interpreter::new(interpreter)?.reads_stdin()
+    {
+        return apply_map_with(interpreter, text, m);
+    }
+
     if let Some(m) = map_fn {
         let cmd = if CONFIG.shell().contains("fish") {
-            format!(r#"printf "%s" "{text}" | {m}"#)
+            let text = shell::quote_word(Syntax::Fish, &text);
+            format!(r#"printf "%s" {text} | {m}"#)
         } else {
             format!(
                 r#"_navi_input() {{
@@ -40,34 +78,89 @@ _navi_input | _navi_map_fn | _navi_nonewline"#
     }
 }
 
-fn get_column(text: String, column: Option<u8>, delimiter: Option<&str>) -> Result<String> {
-    if let Some(c) = column {
-        let mut result = String::from("");
-        let re = regex::Regex::new(delimiter.unwrap_or(r"\s\s+"))
-            .context("Invalid delimiter regex pattern")?;
-        for line in text.split('\n') {
-            if (line).is_empty() {
-                continue;
-            }
-            let mut parts = re.split(line).skip((c - 1) as usize);
-            if !result.is_empty() {
-                result.push('\n');
-            }
-            result.push_str(parts.next().unwrap_or(""));
-        }
-        Ok(result)
+fn column_regex(delimiter: Option<&str>) -> Result<regex::Regex> {
+    regex::Regex::new(delimiter.unwrap_or(r"\s\s+")).context("Invalid delimiter regex pattern")
+}
+
+/// Turns the names of columns into numbers, from the header line of the suggestions.
+pub fn resolve_columns(
+    columns: &[Column],
+    suggestions: &str,
+    header_lines: u8,
+    delimiter: Option<&str>,
+) -> Result<Vec<Column>> {
+    let re = column_regex(delimiter)?;
+    let header: Vec<&str> = if header_lines > 0 {
+        suggestions
+            .lines()
+            .next()
+            .map(|line| re.split(line).map(str::trim).collect())
+            .unwrap_or_default()
     } else {
-        Ok(text)
-    }
+        vec![]
+    };
+
+    columns
+        .iter()
+        .map(|column| match column {
+            Column::Number(_) => Ok(column.clone()),
+            Column::Name(name) if header_lines == 0 => Err(anyhow!(
+                "The column `{name}` can only be found by name with `--header-lines`"
+            )),
+            Column::Name(name) => header
+                .iter()
+                .position(|h| h == name)
+                .map(|i| Column::Number(i + 1))
+                .ok_or_else(|| anyhow!("No column named `{name}` in the header line")),
+        })
+        .collect()
 }
 
-pub fn process(
+fn get_column(
     text: String,
-    column: Option<u8>,
+    columns: Option<&[Column]>,
+    join: Option<&str>,
     delimiter: Option<&str>,
-    map_fn: Option<String>,
 ) -> Result<String> {
-    apply_map(get_column(text, column, delimiter)?, map_fn)
+    let Some(columns) = columns else {
+        return Ok(text);
+    };
+
+    let re = column_regex(delimiter)?;
+    let mut lines = vec![];
+    for line in text.split('\n') {
+        if line.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = re.split(line).collect();
+        let values = columns
+            .iter()
+            .map(|column| match column {
+                Column::Number(n) => Ok(parts.get(n - 1).copied().unwrap_or("")),
+                Column::Name(name) => Err(anyhow!("The column `{name}` wasn't resolved")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        lines.push(values.join(join.unwrap_or(" ")));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Turns the selected lines into a value, as the options of its variable say.
+pub fn process(text: String, opts: &Opts) -> Result<String> {
+    let text = match &opts.json {
+        Some(fields) => json::values(&text, fields),
+        None => text,
+    };
+    let text = get_column(
+        text,
+        opts.column.as_deref(),
+        opts.column_join.as_deref(),
+        opts.delimiter.as_deref(),
+    )?;
+    let text = apply_map(text, opts.map.as_deref(), opts.shell.as_deref())?;
+    let shell = opts.shell.clone().unwrap_or_else(|| CONFIG.shell());
+    opts.format.apply(text, &shell)
 }
 
 pub(super) fn parse_output_single(
@@ -117,6 +210,68 @@ pub(super) fn parse_output_single(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::finder::structures::{Quote, ValueFormat, parse_columns};
+
+    #[test]
+    fn test_format_values() {
+        let opts = Opts {
+            format: ValueFormat {
+                quote: Quote::Double,
+                prefix: Some("--file=".to_string()),
+                join: Some(" ".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let output = process("a b\n$c\n".to_string(), &opts).unwrap();
+        assert_eq!(output, r#"--file="a b" --file="\$c""#);
+
+        let output = process("a\nb".to_string(), &Opts::default());
+        assert_eq!(output.unwrap(), "a\nb");
+
+        // Quotes follow the interpreter of the snippet
+        let quoted = |quote: Quote, shell: &str| {
+            let opts = Opts {
+                format: ValueFormat {
+                    quote,
+                    join: Some(" ".to_string()),
+                    ..Default::default()
+                },
+                shell: Some(shell.to_string()),
+                ..Default::default()
+            };
+            process("it's\n$main".to_string(), &opts)
+        };
+        let shell_quoted = |shell| quoted(Quote::Shell, shell).unwrap();
+        assert_eq!(shell_quoted("bash"), r"'it'\''s' '$main'");
+        assert_eq!(shell_quoted("fish"), r"'it\'s' '$main'");
+        assert_eq!(shell_quoted("pwsh -NoProfile"), "'it''s' '$main'");
+        let double_quoted = |shell| quoted(Quote::Double, shell).unwrap();
+        assert_eq!(double_quoted("fish"), r#""it's" "\$main""#);
+        assert_eq!(double_quoted("pwsh -NoProfile"), r#""it's" "`$main""#);
+        assert!(quoted(Quote::Single, "cmd /C").is_err());
+    }
+
+    #[test]
+    fn test_columns() {
+        let suggestions = "NAME  NAMESPACE  STATUS\nweb  prod  Running\n";
+        let columns = parse_columns("NAMESPACE,1").unwrap();
+        let columns = resolve_columns(&columns, suggestions, 1, None).unwrap();
+        assert_eq!(columns, [Column::Number(2), Column::Number(1)]);
+
+        let opts = Opts {
+            column: Some(columns),
+            column_join: Some("/".to_string()),
+            ..Default::default()
+        };
+        let output = process("web  prod  Running".to_string(), &opts).unwrap();
+        assert_eq!(output, "prod/web");
+
+        let status = parse_columns("STATUS").unwrap();
+        assert!(resolve_columns(&status, suggestions, 0, None).is_err());
+        let age = parse_columns("AGE").unwrap();
+        assert!(resolve_columns(&age, suggestions, 1, None).is_err());
+    }
 
     #[test]
     fn test_parse_output1() {
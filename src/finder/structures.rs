@@ -1,6 +1,9 @@
+use crate::common::quoting::Syntax;
+use crate::common::shell;
 use crate::filesystem;
 use crate::prelude::*;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Opts {
@@ -14,8 +17,22 @@ pub struct Opts {
     pub header: Option<String>,
     pub suggestion_type: SuggestionType,
     pub delimiter: Option<String>,
-    pub column: Option<u8>,
+    pub column: Option<Vec<Column>>,
+    /// Separator between the columns of a value, instead of a space
+    pub column_join: Option<String>,
     pub map: Option<String>,
+    /// How the selected values are quoted and joined
+    pub format: ValueFormat,
+    /// How JSON suggestions are shown, if the suggestions are JSON
+    pub json: Option<JsonFields>,
+    /// Whether the values are written into snippets as is, even with `shell.quote_values`
+    pub raw: bool,
+    /// Program suggestion commands and `--map` functions run with, instead of the configured shell
+    pub shell: Option<String>,
+    /// Whether the snippet runs once per selected value, instead of with all of them
+    pub foreach: bool,
+    /// How many of the `foreach` runs happen at the same time
+    pub parallel: usize,
     pub prevent_select1: bool,
     pub show_all_columns: bool,
     pub env_vars: HashMap<String, String>,
@@ -34,8 +51,15 @@ impl Default for Opts {
             prompt: None,
             suggestion_type: SuggestionType::SingleSelection,
             column: None,
+            column_join: None,
             delimiter: None,
             map: None,
+            format: ValueFormat::default(),
+            json: None,
+            raw: false,
+            shell: None,
+            foreach: false,
+            parallel: 1,
             prevent_select1: true,
             show_all_columns: false,
             env_vars: HashMap::new(),
@@ -95,6 +119,139 @@ impl Opts {
     }
 }
 
+/// A column of the suggestions, as set by `--column`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    /// From 1
+    Number(usize),
+    /// Resolved into a number from the header line, with `--header-lines`
+    Name(String),
+}
+
+impl FromStr for Column {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s.parse::<usize>() {
+            Ok(0) => Err(anyhow!("Columns of `--column` are counted from 1")),
+            Ok(n) => Ok(Self::Number(n)),
+            Err(_) if s.is_empty() => Err(anyhow!("A column of `--column` is empty")),
+            Err(_) => Ok(Self::Name(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Parses the columns of `--column 1,3` or `--column NAME`.
+pub fn parse_columns(s: &str) -> Result<Vec<Column>> {
+    s.split(',').map(str::parse).collect()
+}
+
+/// Formats columns the way [`parse_columns`] reads them.
+pub fn format_columns(columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(Column::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The fields of JSON suggestions, as set by `--json`, `--display` and `--value`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonFields {
+    /// Template of the finder lines, such as `{.name} ({.status})`
+    pub display: Option<String>,
+    /// Path of the value, such as `.metadata.name`, instead of the whole object
+    pub value: Option<String>,
+}
+
+/// How each selected value is quoted, as set by `--quote`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Quote {
+    #[default]
+    None,
+    /// Only when needed, with the syntax of the snippet's interpreter or else the configured shell
+    Shell,
+    Single,
+    Double,
+}
+
+impl Quote {
+    /// The name `--quote` takes, unless values aren't quoted.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Shell => Some("shell"),
+            Self::Single => Some("single"),
+            Self::Double => Some("double"),
+        }
+    }
+}
+
+impl FromStr for Quote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "shell" => Ok(Self::Shell),
+            "single" => Ok(Self::Single),
+            "double" => Ok(Self::Double),
+            _ => Err(anyhow!(
+                "Invalid value `{s}` for `--quote`, expected shell, single, double or none"
+            )),
+        }
+    }
+}
+
+/// How the selected values are written into the snippet, as set by
+/// `--quote`, `--prefix`, `--suffix` and `--join`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValueFormat {
+    pub quote: Quote,
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+    /// Separator between the values, instead of a newline
+    pub join: Option<String>,
+}
+
+impl ValueFormat {
+    /// Formats every non-empty line of `text` as a value, written into a snippet run by `shell`.
+    pub fn apply(&self, text: String, shell: &str) -> Result<String> {
+        if *self == Self::default() {
+            return Ok(text);
+        }
+
+        // Programs which aren't shells get POSIX quotes
+        let syntax = Syntax::of(shell).unwrap_or(Syntax::Posix);
+        let prefix = self.prefix.as_deref().unwrap_or("");
+        let suffix = self.suffix.as_deref().unwrap_or("");
+
+        text.lines()
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                let value = match self.quote {
+                    Quote::None => value.to_string(),
+                    Quote::Shell => shell::quote_word(syntax, value),
+                    Quote::Single => shell::single_quoted(syntax, value)?,
+                    Quote::Double => shell::double_quoted(syntax, value),
+                };
+                Ok(format!("{prefix}{value}{suffix}"))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|values| values.join(self.join.as_deref().unwrap_or("\n")))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SuggestionType {
     /// finder will not print any suggestions
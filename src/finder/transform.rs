@@ -0,0 +1,217 @@
+//! Built-in `--map` functions, such as `@basename` or `@json:.name`, which don't spawn a shell.
+//!
+//! They can be chained with `|`, e.g. `--map "@split:/:2 | @upper"`, and are applied to every line.
+
+use super::json;
+use crate::common::shell;
+use crate::prelude::*;
+use regex::Regex;
+use serde_json::Value;
+
+#[derive(Debug)]
+enum Transform {
+    Basename,
+    Dirname,
+    Lower,
+    Upper,
+    Trim,
+    Quote,
+    /// A field of a JSON object, such as `.metadata.name` or `.items.0`
+    Json(String),
+    /// The nth field, from 1, when splitting by a separator
+    Split(String, usize),
+    /// A substitution, like `sed s/x/y/` does
+    Regex {
+        regex: Regex,
+        replacement: String,
+        global: bool,
+    },
+}
+
+/// Whether a `--map` uses the built-in functions, instead of running with a shell.
+pub fn is_builtin(map_fn: &str) -> bool {
+    map_fn.trim_start().starts_with('@')
+}
+
+fn parse_regex(expr: &str) -> Result<Transform> {
+    let invalid = || anyhow!("Invalid `@regex:{expr}`, expected `s/<regex>/<replacement>/[g]`");
+
+    let rest = expr.strip_prefix('s').ok_or_else(invalid)?;
+    let separator = rest.chars().next().ok_or_else(invalid)?;
+    let parts: Vec<&str> = rest[separator.len_utf8()..].split(separator).collect();
+    let [pattern, replacement, flags] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let global = match *flags {
+        "" => false,
+        "g" => true,
+        _ => return Err(invalid()),
+    };
+
+    Ok(Transform::Regex {
+        regex: Regex::new(pattern).with_context(|| format!("Invalid regex `{pattern}`"))?,
+        replacement: replacement.to_string(),
+        global,
+    })
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+
+        Ok(match (name, arg) {
+            ("@basename", None) => Self::Basename,
+            ("@dirname", None) => Self::Dirname,
+            ("@lower", None) => Self::Lower,
+            ("@upper", None) => Self::Upper,
+            ("@trim", None) => Self::Trim,
+            ("@quote", None) => Self::Quote,
+            ("@json", Some(path)) => Self::Json(path.to_string()),
+            ("@split", Some(arg)) => {
+                let (separator, n) = arg
+                    .rsplit_once(':')
+                    .ok_or_else(|| anyhow!("Invalid `{s}`, expected `@split:<separator>:<n>`"))?;
+                let n =
+                    n.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                        anyhow!("Invalid field `{n}` for `@split`, counting from 1")
+                    })?;
+                Self::Split(separator.to_string(), n)
+            }
+            ("@regex", Some(expr)) => parse_regex(expr)?,
+            _ => return Err(anyhow!("Unknown map function `{s}`")),
+        })
+    }
+}
+
+impl Transform {
+    /// Transforms a line of the value of a snippet run by `shell`.
+    fn apply(&self, line: &str, shell: &str) -> Result<String> {
+        Ok(match self {
+            Self::Basename => Path::new(line)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Self::Dirname => match Path::new(line).parent() {
+                Some(parent) if parent.as_os_str().is_empty() => ".".to_string(),
+                Some(parent) => parent.display().to_string(),
+                None => line.to_string(),
+            },
+            Self::Lower => line.to_lowercase(),
+            Self::Upper => line.to_uppercase(),
+            Self::Trim => line.trim().to_string(),
+            Self::Quote => shell::quote_for_shell(shell, line),
+            Self::Json(path) => {
+                let value: Value = serde_json::from_str(line)
+                    .with_context(|| format!("Invalid JSON for `@json`: {line}"))?;
+                json::field_text(&value, path)
+            }
+            Self::Split(separator, n) => line
+                .split(separator.as_str())
+                .nth(n - 1)
+                .unwrap_or_default()
+                .to_string(),
+            Self::Regex {
+                regex,
+                replacement,
+                global,
+            } => {
+                if *global {
+                    regex.replace_all(line, replacement.as_str()).into_owned()
+                } else {
+                    regex.replace(line, replacement.as_str()).into_owned()
+                }
+            }
+        })
+    }
+}
+
+/// Splits a chain of map functions, keeping the `|` which belong to their arguments,
+/// as in `@regex:s/a|b/c/`.
+fn parse_chain(map_fn: &str) -> Result<Vec<Transform>> {
+    let mut parts: Vec<String> = vec![];
+    for part in map_fn.split('|') {
+        match parts.last_mut() {
+            Some(last) if !part.trim_start().starts_with('@') => {
+                last.push('|');
+                last.push_str(part);
+            }
+            _ => parts.push(part.to_string()),
+        }
+    }
+
+    parts.iter().map(|part| part.parse()).collect()
+}
+
+/// Checks a chain of map functions, so that mistakes show up when cheats are read.
+pub fn validate(map_fn: &str) -> Result<()> {
+    parse_chain(map_fn).map(|_| ())
+}
+
+/// Applies a chain of map functions to every line of `text`, the value of a snippet run by
+/// `shell`.
+pub fn apply(map_fn: &str, text: &str, shell: &str) -> Result<String> {
+    let transforms = parse_chain(map_fn)?;
+
+    let lines = text
+        .lines()
+        .map(|line| {
+            transforms
+                .iter()
+                .try_fold(line.to_string(), |line, transform| {
+                    transform.apply(&line, shell)
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let apply = |map_fn, text| apply(map_fn, text, "bash").unwrap();
+
+        assert_eq!(apply("@basename", "/tmp/a.txt\nsrc/b.rs"), "a.txt\nb.rs");
+        assert_eq!(apply("@dirname", "/tmp/a.txt\nb.rs"), "/tmp\n.");
+        assert_eq!(apply("@trim | @upper", "  main  "), "MAIN");
+        assert_eq!(apply("@split:/:2", "origin/main"), "main");
+        assert_eq!(apply("@regex:s/a|b/x/g", "abc"), "xxc");
+        assert_eq!(apply("@regex:s#^v##", "v1.2"), "1.2");
+        assert_eq!(
+            apply(
+                "@json:.metadata.name | @lower",
+                r#"{"metadata": {"name": "Web"}}"#
+            ),
+            "web"
+        );
+        assert_eq!(apply("@json:.ports.0", r#"{"ports": [80, 443]}"#), "80");
+    }
+
+    #[test]
+    fn test_quote() {
+        let quote = |shell| apply("@quote", "it's", shell).unwrap();
+
+        assert_eq!(quote("bash"), r"'it'\''s'");
+        assert_eq!(quote("fish"), r"'it\'s'");
+        assert_eq!(quote("pwsh -NoProfile"), "'it''s'");
+        // Programs which aren't shells get POSIX quotes
+        assert_eq!(quote("python3"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(validate("@basename | @nope").is_err());
+        assert!(validate("@split:/:0").is_err());
+        assert!(validate("@regex:s/a/b").is_err());
+        assert!(validate("@regex:s/(/b/").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! The history of the snippets navi executed, copied or printed.
+//!
+//! Every entry is appended as a line of JSON to the history file, which is never rewritten.
+
+use crate::commands::core::actor::PreparedSnippet;
+use crate::common::types::VariableCache;
+use crate::env_var;
+use crate::prelude::*;
+use crate::structures::item::Item;
+use std::collections::BTreeMap;
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::ExitStatus;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Executed,
+    Copied,
+    Printed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    /// UTC time, such as `2024-05-01T09:30:00Z`
+    pub time: String,
+    /// Directory navi was run from
+    pub cwd: String,
+    pub id: String,
+    pub tags: String,
+    pub comment: String,
+    pub action: Action,
+    /// The command line, as executed, copied or printed
+    pub command: String,
+    /// Values of the variables, by name
+    pub bindings: BTreeMap<String, String>,
+    /// The snippet, as it runs again
+    pub run: PreparedSnippet,
+    /// Lines selected for the variables whose values differ from them, e.g. because of `--column`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selections: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<String>,
+    /// Exit code of executed snippets, if they weren't killed by a signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_status: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+impl Entry {
+    pub fn new(
+        item: &Item,
+        action: Action,
+        run: &PreparedSnippet,
+        bindings: &VariableCache,
+    ) -> Result<Self> {
+        Ok(Self {
+            time: utc_time(SystemTime::now()),
+            cwd: std::env::current_dir()
+                .map(|cwd| cwd.display().to_string())
+                .unwrap_or_default(),
+            id: item.id.clone(),
+            tags: item.tags.clone(),
+            comment: item.comment.clone(),
+            action,
+            command: run.command_line()?,
+            bindings: bindings
+                .iter()
+                .map(|(name, value)| (env_var::escape(name), value.trim_end_matches('\n').into()))
+                .collect(),
+            run: run.clone(),
+            selections: BTreeMap::new(),
+            confirm: item.confirm.clone(),
+            exit_status: None,
+            duration_ms: None,
+        })
+    }
+
+    pub fn with_selections(self, selections: &VariableCache) -> Self {
+        let selections = selections
+            .iter()
+            .map(|(name, line)| {
+                (
+                    env_var::escape(name),
+                    line.trim_end_matches('\n').to_string(),
+                )
+            })
+            .filter(|(name, line)| self.bindings.get(name) != Some(line))
+            .collect();
+        Self { selections, ..self }
+    }
+
+    /// The lines selected for the variables, or their values when they were selected as is.
+    pub fn previous_selections(&self) -> VariableCache {
+        let mut selections: VariableCache = self.bindings.clone().into_iter().collect();
+        selections.extend(self.selections.clone());
+        selections
+    }
+
+    pub fn with_outcome(self, status: ExitStatus, duration: Duration) -> Self {
+        Self {
+            exit_status: status.code(),
+            duration_ms: Some(duration.as_millis() as u64),
+            ..self
+        }
+    }
+}
+
+/// Formats a time as `YYYY-MM-DDTHH:MM:SSZ`.
+fn utc_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Converts days since 1970-01-01 into a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Appends an entry to the history, if it's enabled.
+pub fn record(entry: &Entry) -> Result<()> {
+    match CONFIG.history_path() {
+        Some(path) => append(&path, entry),
+        None => Ok(()),
+    }
+}
+
+/// Only the user can read the history, as commands may have secrets in them.
+#[cfg(unix)]
+fn private(dir: &mut DirBuilder, file: &mut OpenOptions) {
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+    dir.mode(0o700);
+    file.mode(0o600);
+}
+
+#[cfg(not(unix))]
+fn private(_dir: &mut DirBuilder, _file: &mut OpenOptions) {}
+
+fn append(path: &Path, entry: &Entry) -> Result<()> {
+    let mut dir_builder = DirBuilder::new();
+    let mut options = OpenOptions::new();
+    dir_builder.recursive(true);
+    options.create(true).append(true);
+    private(&mut dir_builder, &mut options);
+
+    if let Some(dir) = path.parent() {
+        dir_builder
+            .create(dir)
+            .with_context(|| format!("Failed to create `{}`", dir.display()))?;
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to open the history file `{}`", path.display()))?;
+
+    // A single write per entry, so that concurrent navi processes don't interleave lines
+    let line = format!("{}\n", serde_json::to_string(entry)?);
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write to `{}`", path.display()))?;
+
+    Ok(())
+}
+
+/// Records an entry, warning instead of failing, as the snippet already ran.
+pub fn record_or_warn(entry: Result<Entry>) {
+    if let Err(e) = entry.and_then(|entry| record(&entry)) {
+        eprintln!("Warning: unable to record the snippet in the history: {e:#}");
+    }
+}
+
+/// Reads the history, from the oldest entry to the newest. Unreadable lines are skipped.
+pub fn read() -> Result<Vec<Entry>> {
+    let Some(path) = CONFIG.history_path() else {
+        return Err(anyhow!("The history is disabled by `history.enabled`"));
+    };
+    read_from(&path)
+}
+
+fn read_from(path: &Path) -> Result<Vec<Entry>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open the history file `{}`", path.display()))?;
+
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_time() {
+        let time = |secs| utc_time(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(time(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(time(1_714_555_800), "2024-05-01T09:30:00Z");
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let root = std::env::temp_dir().join(format!("navi-history-{}", std::process::id()));
+        let path = root.join("navi/history.jsonl");
+        assert!(read_from(&path).unwrap().is_empty());
+
+        let mut item = Item::new(Some(0));
+        item.id = "git/checkout".to_string();
+        item.comment = "Change branch".to_string();
+        item.snippet = "git checkout <branch>".to_string();
+        let mut bindings = VariableCache::new();
+        bindings.insert("branch".to_string(), "main\n".to_string());
+        let run = PreparedSnippet::new(
+            "git checkout main".to_string(),
+            None,
+            Some("/srv/app".to_string()),
+            vec![],
+        );
+
+        let entry = Entry::new(&item, Action::Executed, &run, &bindings).unwrap();
+        append(&path, &entry).unwrap();
+        append(
+            &path,
+            &Entry {
+                action: Action::Printed,
+                ..entry
+            },
+        )
+        .unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let entries = read_from(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "git/checkout");
+        assert_eq!(entries[0].action, Action::Executed);
+        assert_eq!(entries[0].command, "cd /srv/app && git checkout main");
+        assert_eq!(entries[0].bindings["branch"], "main");
+        assert_eq!(entries[0].run.snippet, "git checkout main");
+        assert_eq!(entries[1].action, Action::Printed);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| path.metadata().unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&path), 0o600);
+            assert_eq!(mode(path.parent().unwrap()), 0o700);
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_selections() {
+        let item = Item::new(Some(0));
+        let run = PreparedSnippet::new("kill 42".to_string(), None, None, vec![]);
+        let bindings = VariableCache::from([
+            ("pid".to_string(), "42".to_string()),
+            ("signal".to_string(), "9".to_string()),
+        ]);
+        // Selected with `--column 2`, and selected as is
+        let selections = VariableCache::from([
+            ("pid".to_string(), "root  42  /usr/bin/navi\n".to_string()),
+            ("signal".to_string(), "9".to_string()),
+        ]);
+
+        let entry = Entry::new(&item, Action::Executed, &run, &bindings)
+            .unwrap()
+            .with_selections(&selections);
+        assert_eq!(
+            entry.selections,
+            BTreeMap::from([("pid".to_string(), "root  42  /usr/bin/navi".to_string())])
+        );
+
+        let entry: Entry = serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+        let previous = entry.previous_selections();
+        assert_eq!(previous["pid"], "root  42  /usr/bin/navi");
+        assert_eq!(previous["signal"], "9");
+    }
+}
@@ -5,14 +5,21 @@ mod display;
 mod env_var;
 mod filesystem;
 mod finder;
+mod history;
 mod parser;
+mod policy;
 pub mod prelude;
 mod preview_context;
 mod structures;
+mod tag_rules;
 mod welcome;
 
 mod libs {
     pub mod dns_common;
 }
 
-pub use {commands::handle, filesystem::default_config_pathbuf};
+pub use {
+    commands::core::actor::ExecutionFailed,
+    commands::handle,
+    filesystem::{default_config_pathbuf, log_pathbuf},
+};
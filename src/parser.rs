@@ -1,11 +1,15 @@
 use crate::common::fs;
 use crate::display;
-use crate::finder::structures::{Opts as FinderOpts, SuggestionType};
+use crate::finder::structures::{self, Opts as FinderOpts, SuggestionType};
+use crate::finder::transform;
 use crate::prelude::*;
 use crate::structures::cheat::VariableMap;
 use crate::structures::item::Item;
+use crate::tag_rules::TagRules;
 use std::env;
 use std::io::Write;
+use std::mem;
+use std::process::Stdio;
 
 use std::sync::LazyLock;
 
@@ -34,8 +38,22 @@ fn parse_opts(text: &str) -> Result<FinderOpts> {
                     prevent_extra = true;
                     false
                 }
+                "--foreach" => {
+                    opts.foreach = true;
+                    false
+                }
+                "--raw" => {
+                    opts.raw = true;
+                    false
+                }
+                "--json" => {
+                    opts.json.get_or_insert_default();
+                    false
+                }
                 "--expand" => {
+                    // The values are already quoted
                     opts.map = Some(format!("{} fn map::expand", fs::exe_string()));
+                    opts.raw = true;
                     false
                 }
                 _ => true,
@@ -53,12 +71,33 @@ fn parse_opts(text: &str) -> Result<FinderOpts> {
                     }
                     "--column" => {
                         opts.column = Some(
-                            value
-                                .parse::<u8>()
-                                .context("Value for `--column` is invalid u8")?,
+                            structures::parse_columns(value)
+                                .context("Value for `--column` is invalid")?,
                         )
                     }
-                    "--map" => opts.map = Some(value.to_string()),
+                    "--column-join" => opts.column_join = Some(value.to_string()),
+                    "--parallel" => {
+                        opts.foreach = true;
+                        opts.parallel = value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .context("Value for `--parallel` must be a positive number")?
+                    }
+                    "--map" => {
+                        if transform::is_builtin(value) {
+                            transform::validate(value)?;
+                        }
+                        opts.map = Some(value.to_string())
+                    }
+                    "--quote" => opts.format.quote = value.parse()?,
+                    "--prefix" => opts.format.prefix = Some(value.to_string()),
+                    "--suffix" => opts.format.suffix = Some(value.to_string()),
+                    "--join" => opts.format.join = Some(value.to_string()),
+                    "--display" => {
+                        opts.json.get_or_insert_default().display = Some(value.to_string())
+                    }
+                    "--value" => opts.json.get_or_insert_default().value = Some(value.to_string()),
                     "--delimiter" => opts.delimiter = Some(value.to_string()),
                     "--query" => opts.query = Some(value.to_string()),
                     "--filter" => opts.filter = Some(value.to_string()),
@@ -77,6 +116,12 @@ fn parse_opts(text: &str) -> Result<FinderOpts> {
         })
         .context("Failed to parse finder options")?;
 
+    // Running once per value only makes sense if several values can be selected,
+    // which must stay on their own lines
+    let multi = multi || opts.foreach;
+    if opts.foreach {
+        opts.format.join = None;
+    }
     let suggestion_type = match (multi, prevent_extra) {
         (true, _) => SuggestionType::MultipleSelections, // multi wins over prevent-extra
         (false, false) => SuggestionType::SingleRecommendation,
@@ -119,30 +164,99 @@ fn without_prefix(line: &str) -> String {
     }
 }
 
+const DEFAULT_CONFIRM_MESSAGE: &str = "This snippet is marked as requiring confirmation";
+
+/// Reads a `; key: value` metacomment into the item it describes.
+fn read_metacomment(item: &mut Item, line: &str) {
+    // path filter
+    if let Some(path) = line.strip_prefix("; path:") {
+        item.path_filter = Some(path.trim().into());
+    }
+    // os filter
+    else if let Some(os) = line.strip_prefix("; os:") {
+        item.os_filter = Some(os.trim().into());
+    }
+    // hostname filter
+    else if let Some(hostname) = line.strip_prefix("; hostname:") {
+        item.hostname_filter = Some(hostname.trim().into());
+    }
+    // environment filter
+    else if let Some(env) = line.strip_prefix("; env:") {
+        item.env_filter = Some(env.trim().into());
+    }
+    // git remote filter
+    else if let Some(git_remote) = line.strip_prefix("; git-remote:") {
+        item.git_remote_filter = Some(git_remote.trim().into());
+    }
+    // required binaries
+    else if let Some(requires) = line.strip_prefix("; requires:") {
+        item.requires = Some(requires.trim().into());
+    }
+    // stable identifier
+    else if let Some(id) = line.strip_prefix("; id:") {
+        item.id = id.trim().into();
+    }
+    // confirmation before execution
+    else if let Some(message) = line.strip_prefix("; confirm:") {
+        item.confirm = Some(message.trim().into());
+    } else if line.trim_end() == "; confirm" {
+        item.confirm = Some(DEFAULT_CONFIRM_MESSAGE.into());
+    }
+    // interpreter
+    else if let Some(shell) = line.strip_prefix("; shell:") {
+        item.shell = Some(shell.trim().into());
+    } else if let Some(lang) = line.strip_prefix("; lang:") {
+        item.shell = Some(match lang.split_once(" via ") {
+            Some((_, command)) => command.trim().into(),
+            None => default_interpreter(lang.trim()).into(),
+        });
+    }
+    // working directory and environment
+    else if let Some(cwd) = line.strip_prefix("; cwd:") {
+        item.cwd = Some(cwd.trim().into());
+    } else if let Some(setenv) = line.strip_prefix("; setenv:")
+        && let Some((key, value)) = setenv.split_once('=')
+    {
+        let key = key.trim();
+        item.env.retain(|(k, _)| k != key);
+        item.env.push((key.into(), value.trim().into()));
+    }
+}
+
+/// The program running the snippets of `; lang: <lang>`, when no `via` command is given.
+fn default_interpreter(lang: &str) -> &str {
+    match lang {
+        "python" | "py" => "python3",
+        "javascript" | "js" => "node",
+        "powershell" | "ps1" => "pwsh",
+        "shell" => "sh",
+        lang => lang,
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct FilterOpts {
-    pub allowlist: Vec<String>,
-    pub denylist: Vec<String>,
-    pub hash: Option<u64>,
+    pub tags: Option<TagRules>,
+    pub id: Option<String>,
 }
 
 pub struct Parser<'a> {
     pub variables: VariableMap,
     visited_lines: HashSet<u64>,
+    ids: HashMap<String, u64>,
+    /// IDs shared by more than one snippet, across all cheatsheet paths
+    pub duplicate_ids: Vec<String>,
+    items: Option<Vec<Item>>,
+    available_binaries: HashMap<String, bool>,
+    /// Whether snippets whose binaries are missing are shown dimmed instead of hidden
+    show_unavailable: bool,
     filter: FilterOpts,
     writer: &'a mut dyn Write,
+    /// Whether writing to the finder failed, e.g. because it exited and closed its stdin
+    writer_closed: bool,
     write_fn: fn(&Item) -> String,
 }
 
-fn without_first(string: &str) -> String {
-    string
-        .char_indices()
-        .next()
-        .and_then(|(i, _)| string.get(i + 1..))
-        .unwrap_or("")
-        .to_string()
-}
-
 fn get_current_os() -> String {
     std::env::consts::OS.to_string()
 }
@@ -177,19 +291,17 @@ fn should_show_for_path(path_filter: &Option<String>) -> bool {
         .any(|pattern| matches_path_pattern(&current_dir.to_string_lossy(), pattern))
 }
 
-fn should_show_for_os(os_filter: &Option<String>) -> bool {
-    let Some(filter) = os_filter else {
-        return true;
-    };
-
-    let current_os = get_current_os();
-
-    for os_rule in filter.split(',').map(str::trim) {
-        if let Some(excluded_os) = os_rule.strip_prefix('!') {
-            if current_os == excluded_os {
+/// Evaluates comma-separated rules, where rules starting with `!` are negations.
+///
+/// Rules are checked in order: a matching negation hides the item and a matching rule shows it.
+/// If none of them matches, the item is shown only if all rules are negations.
+fn should_show_for_rules(filter: &str, matches: impl Fn(&str) -> bool) -> bool {
+    for rule in filter.split(',').map(str::trim) {
+        if let Some(excluded) = rule.strip_prefix('!') {
+            if matches(excluded) {
                 return false;
             }
-        } else if current_os == os_rule {
+        } else if matches(rule) {
             return true;
         }
     }
@@ -197,6 +309,16 @@ fn should_show_for_os(os_filter: &Option<String>) -> bool {
     !filter.split(',').any(|s| !s.trim().starts_with('!'))
 }
 
+fn should_show_for_os(os_filter: &Option<String>) -> bool {
+    let Some(filter) = os_filter else {
+        return true;
+    };
+
+    let current_os = get_current_os();
+
+    should_show_for_rules(filter, |os| current_os == os)
+}
+
 fn get_current_hostname() -> String {
     hostname::get()
         .ok()
@@ -211,61 +333,131 @@ fn should_show_for_hostname(hostname_filter: &Option<String>) -> bool {
 
     let current_hostname = get_current_hostname();
 
-    for hostname_rule in filter.split(',').map(str::trim) {
-        if let Some(excluded_hostname) = hostname_rule.strip_prefix('!') {
-            if current_hostname == excluded_hostname {
-                return false;
-            }
-        } else if current_hostname == hostname_rule {
-            return true;
-        }
-    }
+    should_show_for_rules(filter, |hostname| current_hostname == hostname)
+}
 
-    !filter.split(',').any(|s| !s.trim().starts_with('!'))
+/// Matches a whole text against a pattern where `*` matches any characters and `?` a single one.
+pub(crate) fn matches_glob(text: &str, pattern: &str) -> bool {
+    let pattern_regex = regex::escape(pattern.trim())
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+
+    Regex::new(&format!("^{pattern_regex}$"))
+        .map(|re| re.is_match(text))
+        .unwrap_or(false)
+}
+
+fn should_show_for_env(env_filter: &Option<String>) -> bool {
+    let Some(filter) = env_filter else {
+        return true;
+    };
+
+    should_show_for_rules(filter, |rule| match rule.split_once('=') {
+        Some((name, pattern)) => env::var(name.trim())
+            .map(|value| matches_glob(&value, pattern))
+            .unwrap_or(false),
+        None => env::var_os(rule).is_some(),
+    })
 }
 
-fn gen_lists(tag_rules: &str) -> FilterOpts {
-    let words: Vec<_> = tag_rules.split(',').collect();
+/// Turns the many forms of a git URL into `host/path`, e.g. `git@github.com:acme/navi.git`
+/// and `https://github.com/acme/navi` both become `github.com/acme/navi`.
+fn normalize_git_remote(url: &str) -> String {
+    let url = url.trim();
+    let (url, scp_like) = match url.split_once("://") {
+        Some((_, rest)) => (rest, false),
+        None => (url, true),
+    };
+    let url = url.split_once('@').map_or(url, |(_, host)| host);
+    let url = if scp_like {
+        url.replacen(':', "/", 1)
+    } else {
+        url.to_string()
+    };
 
-    let allowlist = words
-        .iter()
-        .filter(|w| !w.starts_with('!'))
-        .map(|w| w.to_string())
-        .collect();
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_string()
+}
 
-    let denylist = words
-        .iter()
-        .filter(|w| w.starts_with('!'))
-        .map(|w| without_first(w))
-        .collect();
+fn get_current_git_remote() -> Option<&'static str> {
+    static GIT_REMOTE: LazyLock<Option<String>> = LazyLock::new(|| {
+        let output = std::process::Command::new("git")
+            .args(["remote", "get-url", "origin"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let url = String::from_utf8(output.stdout).ok()?;
+        (output.status.success() && !url.trim().is_empty()).then(|| normalize_git_remote(&url))
+    });
+
+    GIT_REMOTE.as_deref()
+}
 
-    FilterOpts {
-        allowlist,
-        denylist,
-        ..Default::default()
-    }
+fn should_show_for_git_remote(git_remote_filter: &Option<String>) -> bool {
+    let Some(filter) = git_remote_filter else {
+        return true;
+    };
+
+    let current_remote = get_current_git_remote();
+
+    should_show_for_rules(filter, |pattern| {
+        current_remote.is_some_and(|remote| matches_glob(remote, &normalize_git_remote(pattern)))
+    })
 }
 
 impl<'a> Parser<'a> {
     pub fn new(writer: &'a mut dyn Write, _is_terminal: bool) -> Self {
         let write_fn = display::terminal::write;
 
-        let filter = match CONFIG.tag_rules() {
-            Some(tr) => gen_lists(&tr),
-            None => Default::default(),
-        };
-
         Self {
             variables: Default::default(),
             visited_lines: Default::default(),
-            filter,
+            ids: Default::default(),
+            duplicate_ids: Default::default(),
+            items: None,
+            available_binaries: Default::default(),
+            show_unavailable: CONFIG.show_unavailable(),
+            filter: Default::default(),
             write_fn,
             writer,
+            writer_closed: false,
         }
     }
 
-    pub fn set_hash(&mut self, hash: u64) {
-        self.filter.hash = Some(hash)
+    pub fn set_id(&mut self, id: &str) {
+        self.filter.id = Some(id.to_string())
+    }
+
+    pub fn set_tag_rules(&mut self, tag_rules: TagRules) {
+        self.filter.tags = Some(tag_rules);
+    }
+
+    /// Keeps a copy of every item written to the finder, to be retrieved with [`Parser::take_items`].
+    pub fn keep_items(&mut self) {
+        self.items = Some(vec![]);
+    }
+
+    pub fn take_items(&mut self) -> Vec<Item> {
+        self.items.take().unwrap_or_default()
+    }
+
+    /// Whether every binary listed in `; requires:` is on the `PATH`.
+    /// Lookups are cached, as many snippets usually require the same binaries.
+    fn has_required_binaries(&mut self, requires: &Option<String>) -> bool {
+        let Some(requires) = requires else {
+            return true;
+        };
+
+        requires
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|binary| !binary.is_empty())
+            .all(|binary| {
+                *self
+                    .available_binaries
+                    .entry(binary.to_string())
+                    .or_insert_with(|| fs::which(binary).is_some())
+            })
     }
 
     fn write_cmd(&mut self, item: &Item) -> Result<()> {
@@ -279,29 +471,26 @@ impl<'a> Parser<'a> {
         }
         self.visited_lines.insert(hash);
 
-        if !self.filter.denylist.is_empty() {
-            for v in &self.filter.denylist {
-                if item.tags.contains(v) {
-                    return Ok(());
-                }
-            }
+        if let Some(h) = self.ids.insert(item.id.clone(), hash)
+            && h != hash
+            && !self.duplicate_ids.contains(&item.id)
+        {
+            warn!("snippet ID `{}` is used by more than one snippet", item.id);
+            eprintln!(
+                "Warning: the ID `{}` is used by more than one snippet, run `navi doctor` to list them",
+                item.id
+            );
+            self.duplicate_ids.push(item.id.clone());
         }
 
-        if !self.filter.allowlist.is_empty() {
-            let mut should_allow = false;
-            for v in &self.filter.allowlist {
-                if item.tags.contains(v) {
-                    should_allow = true;
-                    break;
-                }
-            }
-            if !should_allow {
-                return Ok(());
-            }
+        if let Some(tag_rules) = &self.filter.tags
+            && !tag_rules.matches(&item.tags)
+        {
+            return Ok(());
         }
 
-        if let Some(h) = self.filter.hash
-            && h != hash
+        if let Some(id) = &self.filter.id
+            && id != &item.id
         {
             return Ok(());
         }
@@ -311,6 +500,16 @@ impl<'a> Parser<'a> {
             return Ok(());
         }
 
+        // Filter by environment variables
+        if !should_show_for_env(&item.env_filter) {
+            return Ok(());
+        }
+
+        // Filter by git remote
+        if !should_show_for_git_remote(&item.git_remote_filter) {
+            return Ok(());
+        }
+
         // Filter by OS
         if !should_show_for_os(&item.os_filter) {
             return Ok(());
@@ -321,122 +520,241 @@ impl<'a> Parser<'a> {
             return Ok(());
         }
 
-        let write_fn = self.write_fn;
+        // Filter by required binaries
+        let available = self.has_required_binaries(&item.requires);
+        if !available && !self.show_unavailable {
+            return Ok(());
+        }
+
+        if let Some(items) = &mut self.items {
+            items.push(item.clone());
+        }
+
+        let line = if available {
+            (self.write_fn)(item)
+        } else {
+            display::terminal::write_unavailable(item)
+        };
 
         self.writer
-            .write_all(write_fn(item).as_bytes())
+            .write_all(line.as_bytes())
             .context("Failed to write command to finder's stdin")
     }
 
+    /// Merges the variables of an already parsed cheatsheet and writes its items to the finder.
+    ///
+    /// Once the finder stops reading, items are no longer written, but variables are still
+    /// merged, as the selected snippet may need them.
+    pub fn consume(&mut self, cheat: ParsedCheat) {
+        self.variables.merge(cheat.variables);
+
+        if self.writer_closed {
+            return;
+        }
+
+        for item in &cheat.items {
+            if self.write_cmd(item).is_err() {
+                self.writer_closed = true;
+                break;
+            }
+        }
+    }
+
     pub fn read_lines(
         &mut self,
         lines: impl Iterator<Item = Result<String>>,
         id: &str,
         file_index: Option<usize>,
     ) -> Result<()> {
-        let mut item = Item::new(file_index);
+        let cheat = parse_lines(lines, id, file_index)?;
+        self.consume(cheat);
+        Ok(())
+    }
+}
 
-        let mut should_break = false;
+/// Items and variables read from a single cheatsheet.
+///
+/// Parsing doesn't depend on any state shared between files, so it can happen on any thread;
+/// filtering, deduplication and writing are left to [`Parser::consume`].
+#[derive(Default)]
+pub struct ParsedCheat {
+    pub items: Vec<Item>,
+    pub variables: VariableMap,
+}
 
-        let mut variable_cmd = String::from("");
+impl ParsedCheat {
+    fn push(&mut self, item: &Item, id: &str) {
+        if item.comment.is_empty() || item.snippet.trim().is_empty() {
+            return;
+        }
 
-        for (line_nr, line_result) in lines.enumerate() {
-            let line = line_result.with_context(|| {
-                format!("Failed to read line number {line_nr} in cheatsheet `{id}`")
-            })?;
+        let mut item = item.clone();
+        item.snippet = item
+            .snippet
+            .trim_end_matches(display::LINE_SEPARATOR)
+            .to_string();
+        if item.id.is_empty() {
+            item.id = fallback_id(id, &item);
+        }
+        self.items.push(item);
+    }
+}
 
-            if should_break {
-                break;
-            }
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
 
-            // duplicate
-            // if !item.tags.is_empty() && !item.comment.is_empty() {}
+/// Derives an identifier for snippets without an `; id:` metacomment.
+///
+/// It only depends on the cheatsheet's name, the tags and the description,
+/// so editing a snippet doesn't change it.
+fn fallback_id(cheatsheet: &str, item: &Item) -> String {
+    let name = Path::new(cheatsheet)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    format!(
+        "{}/{}/{}",
+        slugify(&name),
+        slugify(&item.tags),
+        slugify(&item.comment)
+    )
+}
 
-            // blank
-            if line.is_empty() {
-                if !item.snippet.is_empty() {
-                    item.snippet.push_str(display::LINE_SEPARATOR);
-                }
-            }
-            // tag
-            else if line.starts_with('%') {
-                should_break = self.write_cmd(&item).is_err();
-                item.snippet = String::from("");
-                item.tags = without_prefix(&line);
-            }
-            // dependency
-            else if line.starts_with('@') {
-                let tags_dependency = without_prefix(&line);
-                self.variables
-                    .insert_dependency(&item.tags, &tags_dependency);
-            }
-            // path filter
-            else if let Some(path) = line.strip_prefix("; path:") {
-                item.path_filter = Some(path.trim().into());
-            }
-            // os filter
-            else if let Some(os) = line.strip_prefix("; os:") {
-                item.os_filter = Some(os.trim().into());
-            }
-            // hostname filter
-            else if let Some(hostname) = line.strip_prefix("; hostname:") {
-                item.hostname_filter = Some(hostname.trim().into());
-            }
-            // metacomment
-            else if line.starts_with(';') {
+pub fn parse_lines(
+    lines: impl Iterator<Item = Result<String>>,
+    id: &str,
+    file_index: Option<usize>,
+) -> Result<ParsedCheat> {
+    let mut cheat = ParsedCheat::default();
+    let mut item = Item::new(file_index);
+
+    // Metacomments describe the snippet that follows them, so they're kept aside until its
+    // description is read. The ones placed before the first tag line apply to the whole file.
+    let mut file_defaults = Item::new(file_index);
+    let mut pending = Item::new(file_index);
+    let mut seen_tags = false;
+    let mut awaiting_snippet = false;
+
+    let mut variable_cmd = String::from("");
+
+    for (line_nr, line_result) in lines.enumerate() {
+        let line = line_result.with_context(|| {
+            format!("Failed to read line number {line_nr} in cheatsheet `{id}`")
+        })?;
+
+        // blank
+        if line.is_empty() {
+            if !item.snippet.is_empty() {
+                item.snippet.push_str(display::LINE_SEPARATOR);
             }
-            // comment
-            else if line.starts_with('#') {
-                should_break = self.write_cmd(&item).is_err();
-                item.snippet = String::from("");
-                item.comment = without_prefix(&line);
-            }
-            // variable
-            else if !variable_cmd.is_empty() || (line.starts_with('$') && line.contains(':')) {
-                should_break = self.write_cmd(&item).is_err();
-
-                item.snippet = String::from("");
-
-                variable_cmd.push_str(line.trim_end_matches('\\'));
-
-                if !line.ends_with('\\') {
-                    let full_variable_cmd = variable_cmd.clone();
-                    let (variable, command, opts) =
-                        parse_variable_line(&full_variable_cmd).with_context(|| {
-                            format!(
-                                "Failed to parse variable line. See line number {} in cheatsheet `{}`",
-                                line_nr + 1,
-                                id
-                            )
-                        })?;
-                    variable_cmd = String::from("");
-                    self.variables.insert_suggestion(
-                        &item.tags,
-                        variable,
-                        (String::from(command), opts),
-                    );
+        }
+        // tag
+        else if line.starts_with('%') {
+            cheat.push(&item, id);
+            item.snippet = String::from("");
+            item.tags = without_prefix(&line);
+            seen_tags = true;
+            awaiting_snippet = false;
+        }
+        // dependency
+        else if line.starts_with('@') {
+            let tags_dependency = without_prefix(&line);
+            cheat
+                .variables
+                .insert_dependency(&item.tags, &tags_dependency);
+        }
+        // metacomment
+        else if line.starts_with(';') {
+            if awaiting_snippet {
+                read_metacomment(&mut item, &line);
+            } else {
+                if !seen_tags {
+                    // Every snippet of the file would share it
+                    if line.starts_with("; id:") {
+                        return Err(anyhow!(
+                            "`; id:` can only describe a single snippet, not the whole file. See line number {} in cheatsheet `{}`",
+                            line_nr + 1,
+                            id
+                        ));
+                    }
+                    read_metacomment(&mut file_defaults, &line);
                 }
+                read_metacomment(&mut pending, &line);
             }
-            // snippet
-            else {
-                if !item.snippet.is_empty() {
-                    item.snippet.push_str(display::LINE_SEPARATOR);
-                }
-                item.snippet.push_str(&line);
+        }
+        // comment
+        else if line.starts_with('#') {
+            cheat.push(&item, id);
+            item = Item {
+                tags: mem::take(&mut item.tags),
+                comment: without_prefix(&line),
+                line: line_nr + 1,
+                ..mem::replace(&mut pending, file_defaults.clone())
+            };
+            awaiting_snippet = true;
+        }
+        // variable
+        else if !variable_cmd.is_empty() || (line.starts_with('$') && line.contains(':')) {
+            cheat.push(&item, id);
+
+            item.snippet = String::from("");
+            awaiting_snippet = false;
+
+            variable_cmd.push_str(line.trim_end_matches('\\'));
+
+            if !line.ends_with('\\') {
+                let full_variable_cmd = variable_cmd.clone();
+                let (variable, command, opts) = parse_variable_line(&full_variable_cmd)
+                    .with_context(|| {
+                        format!(
+                            "Failed to parse variable line. See line number {} in cheatsheet `{}`",
+                            line_nr + 1,
+                            id
+                        )
+                    })?;
+                variable_cmd = String::from("");
+                // Suggestion commands and `--map` functions run with the file's interpreter,
+                // unless it has variables, which these commands may be prompting for. The
+                // interpreter of the snippet replaces it once it's known
+                let opts = match &file_defaults.shell {
+                    Some(shell) if !display::VAR_REGEX.is_match(shell) => Some(FinderOpts {
+                        shell: Some(shell.clone()),
+                        ..opts.unwrap_or_else(FinderOpts::var_default)
+                    }),
+                    _ => opts,
+                };
+                cheat.variables.insert_suggestion(
+                    &item.tags,
+                    variable,
+                    (String::from(command), opts),
+                );
             }
         }
-
-        if !should_break {
-            let _ = self.write_cmd(&item);
+        // snippet
+        else {
+            awaiting_snippet = false;
+            if !item.snippet.is_empty() {
+                item.snippet.push_str(display::LINE_SEPARATOR);
+            }
+            item.snippet.push_str(&line);
         }
-
-        Ok(())
     }
+
+    cheat.push(&item, id);
+
+    Ok(cheat)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::finder::structures::Quote;
 
     #[test]
     fn test_parse_variable_line() {
@@ -452,6 +770,332 @@ mod tests {
         assert_eq!(opts.suggestion_type, SuggestionType::SingleSelection);
     }
 
+    #[test]
+    fn test_parse_foreach_opts() {
+        let opts = parse_opts("--foreach").unwrap();
+        assert!(opts.foreach);
+        assert_eq!(opts.parallel, 1);
+        assert_eq!(opts.suggestion_type, SuggestionType::MultipleSelections);
+
+        let opts = parse_opts("--parallel 4").unwrap();
+        assert!(opts.foreach);
+        assert_eq!(opts.parallel, 4);
+
+        assert!(parse_opts("--foreach --parallel 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_format_opts() {
+        let opts = parse_opts("--multi --quote shell --prefix '-v ' --join ' '").unwrap();
+        assert_eq!(opts.format.quote, Quote::Shell);
+        assert_eq!(opts.format.prefix.as_deref(), Some("-v "));
+        assert_eq!(opts.format.suffix, None);
+        assert_eq!(opts.format.join.as_deref(), Some(" "));
+
+        // `--expand` is still a `--map` running `navi fn map::expand`, which `--map` overrides
+        let expand = format!("{} fn map::expand", fs::exe_string());
+        let opts = parse_opts("--multi --expand").unwrap();
+        assert_eq!(opts.map.as_deref(), Some(expand.as_str()));
+        assert_eq!(opts.format, Default::default());
+        assert!(opts.raw);
+        for text in ["--map 'tr a b' --expand", "--expand --map 'tr a b'"] {
+            assert_eq!(parse_opts(text).unwrap().map.as_deref(), Some("tr a b"));
+        }
+
+        assert!(
+            parse_opts("--foreach --join ,")
+                .unwrap()
+                .format
+                .join
+                .is_none()
+        );
+        assert!(parse_opts("--quote backticks").is_err());
+        assert!(parse_opts("--raw").unwrap().raw);
+    }
+
+    #[test]
+    fn test_parse_column_opts() {
+        use crate::finder::structures::Column;
+
+        let opts = parse_opts("--column 1,NAME --column-join /").unwrap();
+        assert_eq!(
+            opts.column,
+            Some(vec![Column::Number(1), Column::Name("NAME".to_string())])
+        );
+        assert_eq!(opts.column_join.as_deref(), Some("/"));
+
+        assert!(parse_opts("--column 0").is_err());
+        assert!(parse_opts("--column 1,,2").is_err());
+    }
+
+    #[test]
+    fn test_parse_json_opts() {
+        let opts = parse_opts("--json --display '{.name} ({.id})' --value .id").unwrap();
+        let json = opts.json.unwrap();
+        assert_eq!(json.display.as_deref(), Some("{.name} ({.id})"));
+        assert_eq!(json.value.as_deref(), Some(".id"));
+
+        assert_eq!(parse_opts("--json").unwrap().json, Some(Default::default()));
+        assert!(parse_opts("--multi").unwrap().json.is_none());
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let lines = [
+            "% git, code",
+            "",
+            "# Change branch",
+            "git checkout <branch>",
+            "",
+            "# Show status",
+            "git status",
+            "",
+            "$ branch: git branch --- --prevent-extra",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+
+        let cheat = parse_lines(lines, "git.cheat", Some(2)).unwrap();
+
+        let comments: Vec<_> = cheat.items.iter().map(|i| i.comment.as_str()).collect();
+        assert_eq!(comments, ["Change branch", "Show status"]);
+        assert!(cheat.items.iter().all(|i| i.file_index == Some(2)));
+        assert!(
+            cheat
+                .variables
+                .get_suggestion("git, code", "branch")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_metacomments_describe_the_next_snippet() {
+        let lines = [
+            "; os: linux",
+            "% deploy",
+            "; id: deploy-canary",
+            "# Deploy canary",
+            "deploy --canary",
+            "; hostname: prod",
+            "; confirm",
+            "# Deploy everything",
+            "deploy --all",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+
+        let cheat = parse_lines(lines, "/cheats/ops.cheat", None).unwrap();
+        let [canary, all] = cheat.items.as_slice() else {
+            panic!("expected two items");
+        };
+
+        assert_eq!(canary.id, "deploy-canary");
+        assert_eq!(canary.hostname_filter, None);
+        assert_eq!(canary.os_filter.as_deref(), Some("linux"));
+
+        assert!(all.id.starts_with("ops/deploy/"));
+        assert_eq!(all.hostname_filter.as_deref(), Some("prod"));
+        assert_eq!(all.os_filter.as_deref(), Some("linux"));
+        assert_eq!(canary.confirm, None);
+        assert_eq!(all.confirm.as_deref(), Some(DEFAULT_CONFIRM_MESSAGE));
+    }
+
+    #[test]
+    fn test_file_level_id() {
+        let lines = [
+            "; id: deploy",
+            "% deploy",
+            "# Deploy canary",
+            "deploy --canary",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+
+        let Err(err) = parse_lines(lines, "/cheats/ops.cheat", None) else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            err.to_string(),
+            "`; id:` can only describe a single snippet, not the whole file. See line number 1 in cheatsheet `/cheats/ops.cheat`"
+        );
+    }
+
+    #[test]
+    fn test_confirm() {
+        let lines = [
+            "; confirm",
+            "% kubernetes",
+            "# Get pods",
+            "kubectl get pods",
+            "; confirm: This deletes every pod",
+            "# Delete pods",
+            "kubectl delete pods --all",
+            "; confirmation is not a metacomment navi knows",
+            "# Describe pods",
+            "kubectl describe pods",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+
+        let cheat = parse_lines(lines, "/cheats/k8s.cheat", None).unwrap();
+        let confirms: Vec<Option<&str>> = cheat
+            .items
+            .iter()
+            .map(|item| item.confirm.as_deref())
+            .collect();
+        assert_eq!(
+            confirms,
+            [
+                Some(DEFAULT_CONFIRM_MESSAGE),
+                Some("This deletes every pod"),
+                Some(DEFAULT_CONFIRM_MESSAGE),
+            ]
+        );
+
+        let lines = ["% k8s", "; confirmation", "# Get pods", "kubectl get pods"];
+        let cheat = parse_lines(lines.into_iter().map(|l| Ok(l.to_string())), "k8s", None);
+        assert_eq!(cheat.unwrap().items[0].confirm, None);
+    }
+
+    #[test]
+    fn test_required_binaries() {
+        let cheat = || {
+            [
+                "% tools",
+                "; requires: navi-missing-binary",
+                "# Missing",
+                "navi-missing-binary --help",
+                "# Always",
+                "echo hi",
+            ]
+            .into_iter()
+            .map(|l| Ok(l.to_string()))
+        };
+
+        let mut output = vec![];
+        let mut parser = Parser::new(&mut output, false);
+        parser.show_unavailable = false;
+        parser.keep_items();
+        parser.read_lines(cheat(), "tools", None).unwrap();
+        let comments: Vec<String> = parser.take_items().into_iter().map(|i| i.comment).collect();
+        assert_eq!(comments, ["Always"]);
+
+        // Lookups are cached per binary
+        assert_eq!(
+            parser.available_binaries.get("navi-missing-binary"),
+            Some(&false)
+        );
+        parser
+            .available_binaries
+            .insert("navi-cached-binary".to_string(), true);
+        assert!(parser.has_required_binaries(&Some("navi-cached-binary".to_string())));
+        assert!(
+            !parser.has_required_binaries(&Some(
+                "navi-cached-binary, navi-missing-binary".to_string()
+            ))
+        );
+        drop(parser);
+
+        let mut output = vec![];
+        let mut parser = Parser::new(&mut output, false);
+        parser.show_unavailable = true;
+        parser.read_lines(cheat(), "tools", None).unwrap();
+        drop(parser);
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("Missing") && lines[0].contains("\u{1b}[2m"));
+        assert!(!lines[1].contains("\u{1b}[2m"));
+    }
+
+    #[test]
+    fn test_interpreters() {
+        let lines = [
+            "; lang: python",
+            "% scripts",
+            "# Print the answer",
+            "print(<answer>)",
+            "; lang: sql via psql <dsn>",
+            "# Count users",
+            "select count(*) from users;",
+            "; shell: pwsh",
+            "# Show the date",
+            "Get-Date",
+            "$ answer: print(42)",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+
+        let cheat = parse_lines(lines, "scripts.cheat", None).unwrap();
+        let shells: Vec<_> = cheat.items.iter().map(|i| i.shell.as_deref()).collect();
+        assert_eq!(shells, [Some("python3"), Some("psql <dsn>"), Some("pwsh")]);
+
+        let (_, opts) = cheat.variables.get_suggestion("scripts", "answer").unwrap();
+        assert_eq!(opts.as_ref().unwrap().shell.as_deref(), Some("python3"));
+
+        // The variables of an interpreter can't be prompted for with it
+        let lines = [
+            "; lang: sql via psql <dsn>",
+            "% db",
+            "# Count users",
+            "select count(*) from users;",
+            "$ dsn: cat ~/.dsns",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+        let cheat = parse_lines(lines, "db.cheat", None).unwrap();
+        let (_, opts) = cheat.variables.get_suggestion("db", "dsn").unwrap();
+        assert!(opts.as_ref().is_none_or(|opts| opts.shell.is_none()));
+    }
+
+    #[test]
+    fn test_working_directory_and_environment() {
+        let lines = [
+            "; setenv: AWS_PROFILE=dev",
+            "% aws",
+            "; cwd: <repo>/infra",
+            "; setenv: AWS_PROFILE = prod",
+            "; setenv: AWS_REGION=eu-west-1",
+            "# Apply",
+            "terraform apply",
+            "# List buckets",
+            "aws s3 ls",
+        ]
+        .into_iter()
+        .map(|l| Ok(l.to_string()));
+
+        let cheat = parse_lines(lines, "aws.cheat", None).unwrap();
+        let [apply, list] = cheat.items.as_slice() else {
+            panic!("expected 2 items");
+        };
+
+        assert_eq!(apply.cwd.as_deref(), Some("<repo>/infra"));
+        assert_eq!(
+            apply.env,
+            [
+                ("AWS_PROFILE".to_string(), "prod".to_string()),
+                ("AWS_REGION".to_string(), "eu-west-1".to_string())
+            ]
+        );
+        assert_eq!(list.cwd, None);
+        assert_eq!(list.env, [("AWS_PROFILE".to_string(), "dev".to_string())]);
+    }
+
+    #[test]
+    fn test_fallback_id_ignores_snippet() {
+        let mut item = Item {
+            tags: "git, code".to_string(),
+            comment: "Change branch".to_string(),
+            snippet: "git checkout <branch>".to_string(),
+            ..Default::default()
+        };
+        let id = fallback_id("/home/user/cheats/git.cheat", &item);
+        assert_eq!(id, "git/git-code/change-branch");
+
+        item.snippet = "git switch <branch>".to_string();
+        assert_eq!(id, fallback_id("/home/user/cheats/git.cheat", &item));
+    }
+
     #[test]
     fn test_path_pattern_matching() {
         // Test exact match
@@ -542,6 +1186,51 @@ mod tests {
         let _ = should_show_for_path(&Some("/home/user/*, /var/**".to_string()));
     }
 
+    #[test]
+    fn test_env_filtering() {
+        assert!(should_show_for_env(&None));
+
+        assert!(should_show_for_env(&Some("PATH".to_string())));
+        assert!(!should_show_for_env(&Some("!PATH".to_string())));
+        assert!(!should_show_for_env(&Some(
+            "NAVI_UNSET_VARIABLE".to_string()
+        )));
+        assert!(should_show_for_env(&Some(
+            "!NAVI_UNSET_VARIABLE".to_string()
+        )));
+
+        assert!(should_show_for_env(&Some("PATH=*".to_string())));
+        assert!(!should_show_for_env(&Some("PATH=".to_string())));
+        assert!(!should_show_for_env(&Some(
+            "NAVI_UNSET_VARIABLE=*".to_string()
+        )));
+        assert!(should_show_for_env(&Some(
+            "NAVI_UNSET_VARIABLE, PATH".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(matches_glob("prod", "prod"));
+        assert!(matches_glob("prod-eu", "prod*"));
+        assert!(matches_glob("github.com/acme/navi", "github.com/acme/*"));
+        assert!(!matches_glob("github.com/other/navi", "github.com/acme/*"));
+        assert!(matches_glob("v1", "v?"));
+        assert!(!matches_glob("prod+eu", "prod.eu"));
+    }
+
+    #[test]
+    fn test_normalize_git_remote() {
+        for url in [
+            "git@github.com:acme/navi.git",
+            "https://github.com/acme/navi",
+            "https://user@github.com/acme/navi.git",
+            "ssh://git@github.com/acme/navi.git",
+        ] {
+            assert_eq!(normalize_git_remote(url), "github.com/acme/navi");
+        }
+    }
+
     #[test]
     fn test_hostname_filtering() {
         let current_hostname = get_current_hostname();
@@ -0,0 +1,149 @@
+use crate::config::{Policy, Rule};
+use crate::prelude::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Execution denied by {source_name}: {reason}\n\n{command}")]
+pub struct PolicyDenied {
+    source_name: String,
+    reason: String,
+    command: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// The command may run once the user confirms it
+    Confirm(String),
+}
+
+fn first_match<'a>(rules: &'a [Rule], command: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.is_match(command))
+        .map(Rule::as_str)
+}
+
+fn check_one(
+    source_name: &str,
+    policy: &Policy,
+    snippet: &str,
+    command_line: &str,
+) -> Result<Verdict> {
+    let denied = |reason: String| PolicyDenied {
+        source_name: source_name.to_string(),
+        reason,
+        command: command_line.to_string(),
+    };
+    // `deny` and `require_confirm` rules also apply to the `cd`, the variables and the
+    // interpreter wrapping the snippet, so they can't be used to get around them
+    let first_match_in_either =
+        |rules| first_match(rules, snippet).or_else(|| first_match(rules, command_line));
+
+    if policy.read_only {
+        return Err(denied("snippets can only be printed".to_string()).into());
+    }
+
+    if let Some(pattern) = first_match_in_either(&policy.deny) {
+        return Err(denied(format!("the command matches `{pattern}`")).into());
+    }
+
+    if !policy.allow.is_empty() && first_match(&policy.allow, snippet).is_none() {
+        return Err(denied("the command isn't in the list of allowed commands".to_string()).into());
+    }
+
+    if let Some(pattern) = first_match_in_either(&policy.require_confirm) {
+        return Ok(Verdict::Confirm(format!(
+            "Commands matching `{pattern}` require a confirmation, according to {source_name}"
+        )));
+    }
+
+    Ok(Verdict::Allow)
+}
+
+/// Checks an interpolated snippet against every execution policy, along with the command line
+/// running it, i.e. with its working directory, environment and interpreter.
+///
+/// `allow` rules only match the snippet, so that they can be anchored to its start.
+/// Returns an error if any policy denies it.
+pub fn check(snippet: &str, command_line: &str) -> Result<Verdict> {
+    let mut verdict = Verdict::Allow;
+
+    for (source_name, policy) in CONFIG.policies() {
+        if let Verdict::Confirm(message) = check_one(source_name, policy, snippet, command_line)? {
+            verdict = Verdict::Confirm(message);
+        }
+    }
+
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(toml: &str) -> Policy {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_check_one() {
+        let p = policy(
+            r#"
+            allow = ["^kubectl ", "^git "]
+            deny = ["delete"]
+            require_confirm = ["apply"]
+            "#,
+        );
+
+        assert_eq!(
+            check_one("test", &p, "kubectl get pods", "kubectl get pods").unwrap(),
+            Verdict::Allow
+        );
+        assert!(matches!(
+            check_one(
+                "test",
+                &p,
+                "kubectl apply -f x.yaml",
+                "kubectl apply -f x.yaml"
+            )
+            .unwrap(),
+            Verdict::Confirm(_)
+        ));
+        assert!(check_one("test", &p, "kubectl delete pod x", "kubectl delete pod x").is_err());
+        assert!(check_one("test", &p, "rm -rf /", "rm -rf /").is_err());
+
+        let p = Policy {
+            read_only: true,
+            ..Default::default()
+        };
+        assert!(check_one("test", &p, "ls", "ls").is_err());
+
+        // The working directory and the environment don't hide the snippet from anchored rules
+        let p = policy(
+            r#"
+            allow = ["^kubectl "]
+            deny = ["^rm ", "^psql "]
+            require_confirm = ["^kubectl apply"]
+            "#,
+        );
+        let command_line = "cd /srv && KUBECONFIG=prod kubectl get pods";
+        assert_eq!(
+            check_one("test", &p, "kubectl get pods", command_line).unwrap(),
+            Verdict::Allow
+        );
+        let command_line = "cd /srv && KUBECONFIG=prod kubectl apply -f x.yaml";
+        assert!(matches!(
+            check_one("test", &p, "kubectl apply -f x.yaml", command_line).unwrap(),
+            Verdict::Confirm(_)
+        ));
+        let command_line = "cd /srv && KUBECONFIG=prod rm -rf /";
+        let err = check_one("test", &p, "rm -rf /", command_line).unwrap_err();
+        assert!(err.to_string().contains("matches `^rm `"));
+        let command_line = "psql postgres://db <<'NAVIEOF'\nkubectl \nNAVIEOF";
+        assert!(check_one("test", &p, "kubectl ", command_line).is_err());
+
+        let invalid = toml::from_str::<Policy>(r#"deny = ["("]"#).unwrap_err();
+        assert!(invalid.to_string().contains("invalid regex `(`"));
+    }
+}
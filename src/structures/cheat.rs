@@ -33,6 +33,32 @@ impl VariableMap {
         }
     }
 
+    /// Merges the variables of another cheatsheet into this one.
+    /// Suggestions from `other` take precedence, as if its lines were read after ours.
+    pub fn merge(&mut self, other: VariableMap) {
+        for (k, m) in other.variables {
+            self.variables.entry(k).or_default().extend(m);
+        }
+
+        for (k, v) in other.dependencies {
+            self.dependencies.entry(k).or_default().extend(v);
+        }
+    }
+
+    /// The same variables, whose suggestion commands and `--map` functions run with `shell`,
+    /// such as the interpreter of the snippet they're prompted for.
+    pub fn with_shell(&self, shell: &str) -> VariableMap {
+        let mut variables = self.clone();
+        for (_, opts) in variables
+            .variables
+            .values_mut()
+            .flat_map(HashMap::values_mut)
+        {
+            opts.get_or_insert_with(Opts::var_default).shell = Some(shell.to_string());
+        }
+        variables
+    }
+
     pub fn get_suggestion(&self, tags: &str, variable: &str) -> Option<&Suggestion> {
         let k = fnv(&tags);
 
@@ -57,3 +83,26 @@ impl VariableMap {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_shell() {
+        let mut variables = VariableMap::default();
+        variables.insert_suggestion("db", "table", ("\\dt".to_string(), None));
+        let opts = Opts {
+            shell: Some("python3".to_string()),
+            ..Opts::var_default()
+        };
+        variables.insert_suggestion("db", "limit", ("print(10)".to_string(), Some(opts)));
+
+        let variables = variables.with_shell("psql postgres://db/app");
+        for name in ["table", "limit"] {
+            let (_, opts) = variables.get_suggestion("db", name).unwrap();
+            let shell = opts.as_ref().unwrap().shell.as_deref();
+            assert_eq!(shell, Some("psql postgres://db/app"));
+        }
+    }
+}
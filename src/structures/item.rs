@@ -1,14 +1,30 @@
 use crate::common::hash::fnv;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Item {
+    pub id: String,
     pub tags: String,
     pub comment: String,
     pub snippet: String,
     pub file_index: Option<usize>,
+    /// Whether the snippet comes from a cheatsheet kept in the current project
+    pub local: bool,
+    /// Line of the description in the cheatsheet
+    pub line: usize,
     pub path_filter: Option<String>,
     pub os_filter: Option<String>,
     pub hostname_filter: Option<String>,
+    pub env_filter: Option<String>,
+    pub git_remote_filter: Option<String>,
+    pub requires: Option<String>,
+    /// Message shown when asking for confirmation before executing the snippet
+    pub confirm: Option<String>,
+    /// Program the snippet runs with, instead of the configured shell
+    pub shell: Option<String>,
+    /// Directory the snippet runs from
+    pub cwd: Option<String>,
+    /// Environment variables the snippet runs with, in the order they're set
+    pub env: Vec<(String, String)>,
 }
 
 impl Item {
@@ -0,0 +1,267 @@
+//! Boolean expressions over tags, as used by `--tag-rules`.
+//!
+//! Rules combine tags with `AND`, `OR`, `NOT` (or `&`, `|`, `!`) and parentheses,
+//! e.g. `(k8s OR helm) AND NOT delete`. Tags may contain `*` and `?` wildcards.
+//!
+//! The original comma-separated syntax is still understood: `git,docker,!checkout`
+//! means `(git OR docker) AND NOT checkout`.
+
+use crate::parser::matches_glob;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Invalid tag rules `{rules}`: {reason}")]
+pub struct TagRulesError {
+    rules: String,
+    reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagRules {
+    Tag(String),
+    Not(Box<TagRules>),
+    And(Vec<TagRules>),
+    Or(Vec<TagRules>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Comma,
+    Not,
+    And,
+    Or,
+    Word(String),
+}
+
+fn tokenize(rules: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = rules.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '!' => Token::Not,
+            '&' | '|' => {
+                // `&&` and `||` are accepted as well
+                if chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                if c == '&' { Token::And } else { Token::Or }
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "(),!&|".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+struct ExpressionParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExpressionParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `expr (, expr)*`, where negated parts must all hold and at least one of the others must.
+    fn list(&mut self) -> Result<TagRules, String> {
+        let mut included = vec![];
+        let mut excluded = vec![];
+
+        loop {
+            // Empty parts, such as in `git,`, are ignored
+            if !matches!(self.peek(), None | Some(Token::Comma) | Some(Token::Close)) {
+                match self.or()? {
+                    rule @ TagRules::Not(_) => excluded.push(rule),
+                    rule => included.push(rule),
+                }
+            }
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+
+        if !included.is_empty() {
+            excluded.insert(0, TagRules::Or(included).simplify());
+        }
+        if excluded.is_empty() {
+            return Err("expected a tag".to_string());
+        }
+
+        Ok(TagRules::And(excluded).simplify())
+    }
+
+    fn or(&mut self) -> Result<TagRules, String> {
+        let mut rules = vec![self.and()?];
+        while self.eat(&Token::Or) {
+            rules.push(self.and()?);
+        }
+        Ok(TagRules::Or(rules).simplify())
+    }
+
+    fn and(&mut self) -> Result<TagRules, String> {
+        let mut rules = vec![self.unary()?];
+        while self.eat(&Token::And) {
+            rules.push(self.unary()?);
+        }
+        Ok(TagRules::And(rules).simplify())
+    }
+
+    fn unary(&mut self) -> Result<TagRules, String> {
+        if self.eat(&Token::Not) {
+            return Ok(TagRules::Not(Box::new(self.unary()?)));
+        }
+
+        if self.eat(&Token::Open) {
+            let rules = self.list()?;
+            if !self.eat(&Token::Close) {
+                return Err("missing `)`".to_string());
+            }
+            return Ok(rules);
+        }
+
+        // Consecutive words form a single tag, such as `code review`
+        let mut words = vec![];
+        while let Some(Token::Word(word)) = self.peek() {
+            words.push(word.clone());
+            self.pos += 1;
+        }
+
+        match self.peek() {
+            _ if !words.is_empty() => Ok(TagRules::Tag(words.join(" ").to_lowercase())),
+            Some(Token::Close) => Err("unexpected `)`".to_string()),
+            Some(_) => Err("expected a tag before the operator".to_string()),
+            None => Err("expected a tag at the end".to_string()),
+        }
+    }
+}
+
+impl TagRules {
+    pub fn parse(rules: &str) -> Result<Self, TagRulesError> {
+        let mut parser = ExpressionParser {
+            tokens: tokenize(rules),
+            pos: 0,
+        };
+
+        let error = |reason: String| TagRulesError {
+            rules: rules.to_string(),
+            reason,
+        };
+
+        let parsed = parser.list().map_err(error)?;
+        if parser.pos < parser.tokens.len() {
+            return Err(error("unexpected `)`".to_string()));
+        }
+
+        Ok(parsed)
+    }
+
+    fn simplify(self) -> Self {
+        match self {
+            TagRules::And(mut rules) | TagRules::Or(mut rules) if rules.len() == 1 => {
+                rules.remove(0)
+            }
+            rules => rules,
+        }
+    }
+
+    /// Whether a cheatsheet's tags, e.g. `git, code review`, satisfy the rules.
+    pub fn matches(&self, tags: &str) -> bool {
+        let tags: Vec<_> = tags
+            .split(',')
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        self.matches_tags(&tags)
+    }
+
+    fn matches_tags(&self, tags: &[String]) -> bool {
+        match self {
+            TagRules::Tag(pattern) if pattern.contains(['*', '?']) => {
+                tags.iter().any(|tag| matches_glob(tag, pattern))
+            }
+            TagRules::Tag(pattern) => tags.iter().any(|tag| tag == pattern),
+            TagRules::Not(rules) => !rules.matches_tags(tags),
+            TagRules::And(rules) => rules.iter().all(|r| r.matches_tags(tags)),
+            TagRules::Or(rules) => rules.iter().any(|r| r.matches_tags(tags)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(rules: &str, tags: &str) -> bool {
+        TagRules::parse(rules).unwrap().matches(tags)
+    }
+
+    #[test]
+    fn test_comma_syntax() {
+        assert!(matches("git", "git, code"));
+        assert!(!matches("git", "gitlab"));
+        assert!(matches("git,docker", "docker"));
+        assert!(matches("git,!checkout", "git"));
+        assert!(!matches("git,!checkout", "git, checkout"));
+        assert!(matches("!checkout", "docker"));
+        assert!(matches("git,", "git"));
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert!(matches("docker AND prod", "docker, prod"));
+        assert!(!matches("docker && prod", "docker"));
+        assert!(matches("(k8s OR helm) AND NOT delete", "helm"));
+        assert!(!matches("(k8s | helm) & !delete", "k8s, delete"));
+        assert!(!matches("(k8s OR helm) AND NOT delete", "docker"));
+        assert!(matches("not (a or b)", "c"));
+        assert!(matches("code review", "git, Code Review"));
+    }
+
+    #[test]
+    fn test_globs() {
+        assert!(matches("git*", "gitlab"));
+        assert!(matches("k?s", "k8s"));
+        assert!(!matches("git*", "lazygit"));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        for rules in ["", "git AND", "(git", "git)", "AND git", "!"] {
+            assert!(TagRules::parse(rules).is_err(), "{rules}");
+        }
+    }
+}